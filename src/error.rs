@@ -0,0 +1,88 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use postgres::error::DbError;
+
+/// What went wrong, by where it went wrong, so that callers can tell failures apart
+#[derive(Debug)]
+pub enum AppError {
+    // the database could not be reached or refused a query
+    Db(postgres::Error),
+    // a roster could not be made sense of
+    Roster(String),
+    // a file could not be read or written
+    Io(io::Error),
+    // a setting, or a file of settings, is not valid
+    Config(String),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AppError::Db(e) => write!(f, "{}", e),
+            AppError::Roster(msg) => write!(f, "{}", msg),
+            AppError::Io(e) => write!(f, "{}", e),
+            AppError::Config(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl Error for AppError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AppError::Db(e) => Some(e),
+            AppError::Io(e) => Some(e),
+            AppError::Roster(_) | AppError::Config(_) => None,
+        }
+    }
+}
+
+impl AppError {
+    /// The error reported by the server, if the database refused a query
+    pub fn as_db_error(&self) -> Option<&DbError> {
+        match self {
+            AppError::Db(e) => e.as_db_error(),
+            _ => None,
+        }
+    }
+
+    /// Whether the connection to the database was closed, so that what was sent may or may not
+    /// have been done
+    pub fn is_closed(&self) -> bool {
+        match self {
+            AppError::Db(e) => e.is_closed(),
+            _ => false,
+        }
+    }
+}
+
+impl From<postgres::Error> for AppError {
+    fn from(e: postgres::Error) -> AppError {
+        AppError::Db(e)
+    }
+}
+
+impl From<io::Error> for AppError {
+    fn from(e: io::Error) -> AppError {
+        AppError::Io(e)
+    }
+}
+
+/// Rosters are the only CSV that is parsed, so anything but a failure to read the file is a
+/// problem with a roster; CSV that is only written converts its errors to `io::Error` instead
+impl From<csv::Error> for AppError {
+    fn from(e: csv::Error) -> AppError {
+        if e.is_io_error() {
+            AppError::Io(e.into())
+        } else {
+            AppError::Roster(e.to_string())
+        }
+    }
+}
+
+impl From<toml::de::Error> for AppError {
+    fn from(e: toml::de::Error) -> AppError {
+        AppError::Config(e.to_string())
+    }
+}
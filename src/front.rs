@@ -1,66 +1,605 @@
-use std::collections::HashMap;
-use std::fs::File;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs::{self, File};
 use std::iter::FromIterator;
+use std::path::{Path, PathBuf};
+use std::panic::{self, AssertUnwindSafe};
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use chrono::{Local, Date, Datelike, DateTime, NaiveDate, TimeZone};
+use chrono::{Local, Date, DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
 use cursive::align::HAlign;
-use cursive::traits::Scrollable;
+use cursive::event::{Event, Key};
+use cursive::theme::Effect;
+use cursive::traits::{Scrollable, With};
+use cursive::utils::markup::StyledString;
 use cursive::view::{Boxable, Identifiable};
-use cursive::views::{Button, Checkbox, Dialog, DummyView, EditView, LinearLayout, PaddedView, ResizedView, SelectView, TextView};
+use cursive::views::{Button, Dialog, DummyView, EditView, HideableView, LinearLayout, NamedView, OnEventView, PaddedView, ResizedView, SelectView, TextView};
 use cursive::Cursive;
+use deunicode::deunicode;
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
 
-use crate::back::{EventRecorder, StudentPicker};
-use crate::model::{Category, Student};
-use crate::gate::{export_summary};
+use crate::back::{get_student_picker, EventRecorder, StudentPicker, StudentSort};
+use crate::model::{Category, EventRow, ExportIdentifier, Labels, Satisfaction, Student, UnknownName};
+use crate::gate::{export_detailed_summary, export_range_summary, export_summary, read_labels, read_roster, ExportEncoding, RosterFormat};
+use crate::error::AppError;
 
-pub fn cli(students: Rc<Vec<Student>>, categories: Vec<Category>, picker: StudentPicker, event_recorder: EventRecorder) {
+/// How the user interface behaves, as chosen on the command line
+pub struct Preferences {
+    // seconds between automatically choosing the next student, or 0 to never do so
+    pub next_up_interval: u32,
+    // whether student matches are listed with usernames
+    pub show_usernames: bool,
+    // how typed text is matched against student names
+    pub name_matching: NameMatching,
+    // whether quitting asks for confirmation first
+    pub confirm_quit: bool,
+    // time of day events recorded against yesterday's class are dated
+    pub class_time: NaiveTime,
+    // points below which an enrolled student is listed as at risk
+    pub min_points: i32,
+    // whether the summary is refreshed before quitting
+    pub refresh_on_quit: bool,
+    // how long after moving to a field of the recording dialog enter is ignored
+    pub advance_delay: Duration,
+    // whether the characters of student matches that matched the typed text are emphasized
+    pub highlight_matches: bool,
+}
+
+pub fn cli(students: Rc<Vec<Student>>, categories: Vec<Category>, picker: StudentPicker, event_recorder: EventRecorder, preferences: Preferences) -> Result<(), Box<dyn Error>> {
     let categories = Rc::new(categories);
     let picker = Arc::new(Mutex::new(picker));
     let event_recorder = Arc::new(Mutex::new(event_recorder));
 
     let mut siv = cursive::crossterm();
-    siv.load_theme_file("data/style.toml").unwrap();
+    let theme_warning = load_theme(&mut siv);
+    // the labels keep their usual wording if they cannot be read, as the theme falls back to its default
+    let (labels, labels_error) = match read_labels(Path::new("data/labels.toml")) {
+        Ok(labels) => (labels, None),
+        Err(e) => (Labels::default(), Some(e)),
+    };
+    set_menu_state(&mut siv, &students, &categories, &picker, &event_recorder);
+    siv.with_user_data(|state: &mut MenuState| {
+        state.show_usernames = preferences.show_usernames;
+        state.name_matching = preferences.name_matching;
+        state.confirm_quit = preferences.confirm_quit;
+        state.class_time = preferences.class_time;
+        state.min_points = preferences.min_points;
+        state.refresh_on_quit = preferences.refresh_on_quit;
+        state.advance_delay = preferences.advance_delay;
+        state.highlight_matches = preferences.highlight_matches;
+        state.labels = Rc::new(labels);
+    });
+    siv.add_global_callback(Key::Esc, return_to_main_menu);
+    siv.add_global_callback(Event::CtrlChar('q'), quit);
+    // Ctrl-C would otherwise quit straight away, without confirming or finishing up
+    siv.clear_global_callbacks(Event::CtrlChar('c'));
+    siv.add_global_callback(Event::CtrlChar('c'), quit);
+    if students.is_empty() {
+        siv.add_layer(
+            build_import_dialog(students, categories, picker, event_recorder)
+        );
+    } else {
+        siv.add_layer(
+            build_main_menu(students, categories, picker, event_recorder)
+        );
+    }
+
+    if preferences.next_up_interval > 0 {
+        let cb_sink = siv.cb_sink().clone();
+        let interval = Duration::from_secs(u64::from(preferences.next_up_interval));
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            // sending fails once the user interface has shut down
+            if cb_sink.send(Box::new(advance_next_up)).is_err() {
+                break;
+            }
+        });
+    }
+    if let Some(warning) = theme_warning {
+        siv.add_layer(warning);
+    }
+    if let Some(e) = labels_error {
+        siv.add_layer(Dialog::info(format!("Labels failed to load, using default:\n{}", e)));
+    }
+
+    #[cfg(unix)]
+    let signals_handle = watch_for_signals(&siv)?;
+
+    // a panic message printed while the backend is running would be drawn into the alternate
+    // screen and lost, so hold on to it until the backend has been torn down
+    let panic_msg: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+    let panic_msg_for_hook = Arc::clone(&panic_msg);
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        *panic_msg_for_hook.lock().unwrap() = Some(info.to_string());
+    }));
+    // unwinding out of the runner drops the backend, which restores the terminal
+    let result = panic::catch_unwind(AssertUnwindSafe(|| siv.try_run()));
+    drop(siv);
+    panic::set_hook(default_hook);
+    #[cfg(unix)]
+    signals_handle.close();
+    match result {
+        Ok(r) => r,
+        Err(_) => {
+            let msg = panic_msg.lock().unwrap().take().unwrap_or_else(|| "unknown panic".to_string());
+            Err(format!("Unexpected error:\n{}", msg).into())
+        }
+    }
+}
+
+/// Quits through the user interface when the process is sent a signal to stop, so that the
+/// backend is torn down and the terminal restored instead of being left in raw mode
+///
+/// An interrupt asks for confirmation like Ctrl-C does, while a request to terminate quits right
+/// away. A closed terminal leaves nothing to restore and keeps the user interface from ever
+/// getting to the quit, so the process exits on the spot as it did before signals were watched.
+#[cfg(unix)]
+fn watch_for_signals(siv: &Cursive) -> Result<signal_hook::iterator::Handle, Box<dyn Error>> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+
+    let mut signals = signal_hook::iterator::Signals::new([SIGINT, SIGTERM, SIGHUP])?;
+    let handle = signals.handle();
+    let cb_sink = siv.cb_sink().clone();
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            if signal == SIGHUP {
+                std::process::exit(128 + SIGHUP);
+            }
+            let sent = if signal == SIGINT {
+                cb_sink.send(Box::new(quit))
+            } else {
+                cb_sink.send(Box::new(Cursive::quit))
+            };
+            // sending fails once the user interface has shut down
+            if sent.is_err() {
+                break;
+            }
+        }
+    });
+    Ok(handle)
+}
+
+/// What the main menu is built from, kept as user data so that global callbacks see whatever the
+/// latest roster import left behind
+struct MenuState {
+    students: Rc<Vec<Student>>,
+    categories: Rc<Vec<Category>>,
+    picker: Arc<Mutex<StudentPicker>>,
+    event_recorder: Arc<Mutex<EventRecorder>>,
+    // whether student matches are listed with usernames, to tell apart students with similar names
+    show_usernames: bool,
+    // how typed text is matched against student names when recording and redeeming
+    name_matching: NameMatching,
+    // whether quitting asks for confirmation first, so that a stray press does not end the session
+    confirm_quit: bool,
+    // wording of the recording dialog
+    labels: Rc<Labels>,
+    // time of day events recorded against yesterday's class are dated
+    class_time: NaiveTime,
+    // points below which an enrolled student is listed as at risk, as last chosen
+    min_points: i32,
+    // whether the summary is refreshed before quitting, so that later exports are up to date
+    refresh_on_quit: bool,
+    // how long after moving to a field of the recording dialog enter is ignored, so that a
+    // doubled enter does not skip the next field
+    advance_delay: Duration,
+    // when enter last moved to another field of the recording dialog
+    last_advance: Option<Instant>,
+    // whether the characters of student matches that matched the typed text are emphasized, to
+    // make scanning the matches quicker
+    highlight_matches: bool,
+    // how many times each student was recorded since the program started, shown beside the
+    // recording dialog so that calling on students can be spread around the class
+    session_tally: HashMap<String, u32>,
+    // the last submission that failed with a database error, until it is recorded
+    failed_recording: Option<RecordingAttempt>,
+}
+
+/// Remembers what the main menu should be rebuilt from after the students or categories change
+fn set_menu_state(siv: &mut Cursive, students: &Rc<Vec<Student>>, categories: &Rc<Vec<Category>>, picker: &Arc<Mutex<StudentPicker>>, event_recorder: &Arc<Mutex<EventRecorder>>) {
+    let show_usernames = showing_usernames(siv);
+    let name_matching = current_name_matching(siv);
+    let confirm_quit = siv.user_data::<MenuState>().is_none_or(|state| state.confirm_quit);
+    let labels = current_labels(siv);
+    let class_time = current_class_time(siv);
+    let min_points = current_min_points(siv);
+    let refresh_on_quit = siv.user_data::<MenuState>().is_some_and(|state| state.refresh_on_quit);
+    let advance_delay = siv.user_data::<MenuState>().map_or(Duration::from_millis(0), |state| state.advance_delay);
+    let highlight_matches = highlighting_matches(siv);
+    let session_tally = current_session_tally(siv);
+    let failed_recording = siv.user_data::<MenuState>().and_then(|state| state.failed_recording.clone());
+    siv.set_user_data(MenuState {
+        students: Rc::clone(students),
+        categories: Rc::clone(categories),
+        picker: Arc::clone(picker),
+        event_recorder: Arc::clone(event_recorder),
+        show_usernames: show_usernames,
+        name_matching: name_matching,
+        confirm_quit: confirm_quit,
+        labels: labels,
+        class_time: class_time,
+        min_points: min_points,
+        refresh_on_quit: refresh_on_quit,
+        advance_delay: advance_delay,
+        last_advance: None,
+        highlight_matches: highlight_matches,
+        session_tally: session_tally,
+        failed_recording: failed_recording,
+    });
+}
+
+fn showing_usernames(siv: &mut Cursive) -> bool {
+    siv.user_data::<MenuState>().is_some_and(|state| state.show_usernames)
+}
+
+fn highlighting_matches(siv: &mut Cursive) -> bool {
+    siv.user_data::<MenuState>().is_none_or(|state| state.highlight_matches)
+}
+
+fn current_name_matching(siv: &mut Cursive) -> NameMatching {
+    siv.user_data::<MenuState>().map_or(NameMatching::Fuzzy, |state| state.name_matching)
+}
+
+fn current_labels(siv: &mut Cursive) -> Rc<Labels> {
+    siv.user_data::<MenuState>().map_or_else(|| Rc::new(Labels::default()), |state| Rc::clone(&state.labels))
+}
+
+fn current_class_time(siv: &mut Cursive) -> NaiveTime {
+    siv.user_data::<MenuState>().map_or_else(|| NaiveTime::from_hms(10, 0, 0), |state| state.class_time)
+}
+
+fn current_min_points(siv: &mut Cursive) -> i32 {
+    siv.user_data::<MenuState>().map_or(DEFAULT_MIN_POINTS, |state| state.min_points)
+}
+
+fn current_session_tally(siv: &mut Cursive) -> HashMap<String, u32> {
+    siv.user_data::<MenuState>().map_or_else(HashMap::new, |state| state.session_tally.clone())
+}
+
+/// Checks that enter was not pressed too soon after it last moved to another field of the
+/// recording dialog, saying so in the recording status if it was; otherwise notes this move
+fn advance_allowed(siv: &mut Cursive) -> bool {
+    let now = Instant::now();
+    let allowed = siv.with_user_data(|state: &mut MenuState| {
+        let allowed = state.last_advance.is_none_or(|last| now.duration_since(last) >= state.advance_delay);
+        if allowed {
+            state.last_advance = Some(now);
+        }
+        allowed
+    }).unwrap_or(true);
+    if !allowed {
+        siv.call_on_name("recording_status", |v: &mut TextView| {
+            v.set_content("Enter ignored, coming right after the last one; press it again");
+        });
+    }
+    allowed
+}
+
+/// Quits, first asking for confirmation unless that was turned off
+fn quit(siv: &mut Cursive) {
+    let confirm_quit = siv.user_data::<MenuState>().is_none_or(|state| state.confirm_quit);
+    // asking to quit again while being asked to confirm is taken as confirming
+    let confirming = siv.find_name::<Dialog>("quit_confirm").is_some();
+    if confirm_quit && !confirming {
+        siv.add_layer(
+            Dialog::text("Quit the participation tracker?")
+            .button("Quit", finish)
+            .dismiss_button("Cancel")
+            .with_name("quit_confirm")
+        );
+    } else {
+        finish(siv);
+    }
+}
+
+/// Quits, first refreshing the summary if that was asked for; a failed refresh is reported
+/// rather than quitting, in case it can be fixed first
+fn finish(siv: &mut Cursive) {
+    let event_recorder = match siv.user_data::<MenuState>() {
+        Some(state) if state.refresh_on_quit => Arc::clone(&state.event_recorder),
+        _ => {
+            siv.quit();
+            return;
+        }
+    };
+    // refreshing would be a write, which a read-only session cannot make
+    if event_recorder.lock().unwrap().read_only() {
+        siv.quit();
+        return;
+    }
+    siv.add_layer(Dialog::around(TextView::new("Finalizing...")));
+    // let the message draw before blocking on the database
+    siv.cb_sink().send(Box::new(move |inner_siv: &mut Cursive| {
+        let refreshed = event_recorder.lock().unwrap().refresh_summary();
+        match refreshed {
+            Ok(()) => inner_siv.quit(),
+            Err(e) => {
+                inner_siv.pop_layer();
+                inner_siv.add_layer(
+                    Dialog::text(format!("Could not refresh the summary before quitting:\n{}", e))
+                    .button("Quit anyway", Cursive::quit)
+                    .dismiss_button("Cancel")
+                );
+            }
+        }
+    })).unwrap();
+}
+
+/// Label and value of a student in a list of matches; the value is always the plain name, which
+/// is what the student is looked up by
+///
+/// The characters of the name at `matched`, as found by `NamedFinder::find_with_indices`, are
+/// emphasized in the label.
+fn student_match_item(student: &Student, show_username: bool, matched: &[usize]) -> (StyledString, String) {
+    let mut label = emphasize_chars(&student.name, matched);
+    if let (Some(username), true) = (&student.username, show_username) {
+        label.append_plain(format!(" ({})", username));
+    }
+    (label, student.name.to_string())
+}
+
+/// Styles the characters of the text at the given character indices in bold, leaving the rest
+/// plain; terminals that cannot show bold text show it all plain
+fn emphasize_chars(text: &str, indices: &[usize]) -> StyledString {
+    let mut styled = StyledString::new();
+    let mut run = String::new();
+    let mut run_emphasized = false;
+    for (i, ch) in text.chars().enumerate() {
+        let emphasized = indices.contains(&i);
+        if emphasized != run_emphasized && !run.is_empty() {
+            if run_emphasized {
+                styled.append_styled(&run, Effect::Bold);
+            } else {
+                styled.append_plain(&run);
+            }
+            run.clear();
+        }
+        run_emphasized = emphasized;
+        run.push(ch);
+    }
+    if run_emphasized {
+        styled.append_styled(&run, Effect::Bold);
+    } else {
+        styled.append_plain(&run);
+    }
+    styled
+}
+
+/// Passes over the student suggested as next up in the main menu in favor of the one after them
+///
+/// Nothing changes unless the main menu is showing, so that the suggestion is not moved while an
+/// event is being recorded.
+fn advance_next_up(siv: &mut Cursive) {
+    if siv.find_name::<TextView>("next_up").is_none() {
+        return;
+    }
+    let picker = match siv.user_data::<MenuState>() {
+        Some(state) => Arc::clone(&state.picker),
+        None => return,
+    };
+    let msg = {
+        let mut picker = picker.lock().unwrap();
+        picker.defer_next();
+        // keep the order of the current pass in case of a restart; a failure here has nowhere to
+        // be shown, and the next random choice will save (and report) again
+        let _ = picker.autosave();
+        format_next_up(&mut picker)
+    };
+    siv.call_on_name("next_up", |v: &mut TextView| {
+        v.set_content(msg);
+    });
+}
+
+fn format_next_up(picker: &mut StudentPicker) -> String {
+    match picker.peek() {
+        Some(student) => format!("Next up: {}", student.name),
+        None => "Next up: no students qualify".to_string(),
+    }
+}
+
+/// Drops every open dialog and shows a freshly built main menu in their place
+///
+/// Until a roster has been imported there is no main menu to go back to, so this does nothing.
+fn return_to_main_menu(siv: &mut Cursive) {
+    let (students, categories, picker, event_recorder) = match siv.user_data::<MenuState>() {
+        Some(state) => (
+            Rc::clone(&state.students),
+            Rc::clone(&state.categories),
+            Arc::clone(&state.picker),
+            Arc::clone(&state.event_recorder),
+        ),
+        None => return,
+    };
+    if students.is_empty() {
+        return;
+    }
+    while siv.pop_layer().is_some() {}
+    siv.add_layer(build_main_menu(students, categories, picker, event_recorder));
+}
+
+/// Points below which a student is listed as at risk when `--min-points` is not given
+pub const DEFAULT_MIN_POINTS: i32 = 5;
+
+/// Matches scoring below this are dropped; a lone character matched in the middle of a word
+/// scores 15, while one matched at the start of a word scores about twice that
+const DEFAULT_MIN_SCORE: i64 = 16;
+
+/// Loads the theme, returning a dialog to show if it could not be loaded
+///
+/// A missing or malformed theme should not keep the tool from being used, so the default theme
+/// stays in place instead.
+fn load_theme(siv: &mut Cursive) -> Option<Dialog> {
+    siv.load_theme_file("data/style.toml").err()
+        .map(|e| Dialog::info(format!("Theme failed to load, using default:\n{:?}", e)))
+}
+
+/// Asks the user which of the given schemas to work in
+pub fn choose_schema(schemas: &[String]) -> Result<String, Box<dyn Error>> {
+    let chosen: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+    let chosen_for_submit = Rc::clone(&chosen);
+
+    let mut siv = cursive::crossterm();
+    let theme_warning = load_theme(&mut siv);
     siv.add_layer(
-        build_main_menu(students, categories, picker, event_recorder)
+        Dialog::around(
+            LinearLayout::vertical()
+            .child(
+                TextView::new("Choose schema:")
+            )
+            .child(
+                SelectView::<String>::new()
+                .with_all_str(schemas.iter().cloned())
+                .on_submit(move |siv: &mut Cursive, schema: &String| {
+                    *chosen_for_submit.borrow_mut() = Some(schema.to_string());
+                    siv.quit();
+                })
+                .scrollable()
+            )
+        )
+        .title("Schemas")
+        .button("Quit", Cursive::quit)
     );
-    siv.run();
+    if let Some(warning) = theme_warning {
+        siv.add_layer(warning);
+    }
+    siv.try_run()?;
+    drop(siv);
+
+    let chosen = chosen.borrow_mut().take();
+    chosen.ok_or_else(|| "No schema chosen".into())
+}
 
-    ()
+/// How a `NamedFinder` decides whether a name matches the typed text
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NameMatching {
+    // skim's fuzzy matching, ranking closer matches first
+    Fuzzy,
+    // names starting with the text
+    Prefix,
+    // names containing the text, ranking those where it appears earlier first
+    Substring,
 }
 
 struct NamedFinder<T: Named> {
     items: Rc<Vec<T>>,
+    // names with diacritics stripped and case folded, in the same order as items
+    normalized_names: Vec<String>,
+    // for each character of each normalized name, the index of the character of the name it
+    // came from
+    origins: Vec<Vec<usize>>,
+    // index of each item by its exact name, for checking whether typed text names an item
+    by_name: HashMap<String, usize>,
+    matching: NameMatching,
     matcher: SkimMatcherV2,
+    min_score: i64,
+    max_results: Option<usize>,
 }
 
 impl<T: Named> NamedFinder<T> {
-    fn new(items: Rc<Vec<T>>) -> NamedFinder<T> {
+    fn new(items: Rc<Vec<T>>, matching: NameMatching) -> NamedFinder<T> {
+        NamedFinder::new_with_threshold(items, matching, DEFAULT_MIN_SCORE, None)
+    }
+
+    /// Creates a finder that drops fuzzy matches scoring below `min_score` and, if `max_results`
+    /// is given, returns at most that many of the best matches
+    fn new_with_threshold(items: Rc<Vec<T>>, matching: NameMatching, min_score: i64, max_results: Option<usize>) -> NamedFinder<T> {
+        let (normalized_names, origins) = items.iter()
+            .map(|item| normalize_with_origins(item.get_name()))
+            .unzip();
+        let by_name = items.iter()
+            .enumerate()
+            .map(|(i, item)| (item.get_name().to_string(), i))
+            .collect();
         NamedFinder {
             items: items,
+            normalized_names: normalized_names,
+            origins: origins,
+            by_name: by_name,
+            matching: matching,
             matcher: SkimMatcherV2::default(),
+            min_score: min_score,
+            max_results: max_results,
         }
     }
 
+    /// Retrieves the item with exactly this name, if any
+    fn exact(&self, name: &str) -> Option<&T> {
+        self.by_name.get(name).and_then(|i| self.items.get(*i))
+    }
+
     fn find<'a>(&'a self, query: &str) -> Vec<&'a T> {
+        self.find_with_indices(query).into_iter()
+            .map(|(item, _)| item)
+            .collect()
+    }
+
+    /// Finds the items like `find`, along with the indices of the characters of each item's name
+    /// that matched the query
+    fn find_with_indices<'a>(&'a self, query: &str) -> Vec<(&'a T, Vec<usize>)> {
+        let query = normalize_for_matching(query);
         let mut found_scores_names_things = Vec::from_iter(self.items.iter()
+            .zip(self.normalized_names.iter())
             .enumerate()
-            .filter_map(|(i, item)| match self.matcher.fuzzy_match(item.get_name(), query) {
-                Some(score) => Some((score, item.get_name(), i)),
-                None => None
-            })
+            .filter_map(|(i, (item, normalized))| self.score(normalized, &query)
+                .map(|(score, indices)| (score, item.get_name(), i, indices))
+            )
         );
-        found_scores_names_things.sort();
-        Vec::from_iter(found_scores_names_things.iter()
-            // go from highest to lowest score
-            .rev()
-            // keep only reference to Named struct reference
-            .filter_map(|a| self.items.get(a.2))
+        // go from highest to lowest score, alphabetically among equal scores
+        found_scores_names_things.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+        Vec::from_iter(found_scores_names_things.into_iter()
+            .take(self.max_results.unwrap_or(usize::MAX))
+            .filter_map(|(_, _, i, indices)| {
+                // several normalized characters can come from one character of the name
+                let mut matched: Vec<usize> = indices.iter().map(|j| self.origins[i][*j]).collect();
+                matched.dedup();
+                self.items.get(i).map(|item| (item, matched))
+            })
         )
     }
+
+    /// Scores how well the normalized name matches the normalized query, if it matches at all,
+    /// along with the indices of the characters of the normalized name that matched
+    fn score(&self, normalized: &str, query: &str) -> Option<(i64, Vec<usize>)> {
+        let query_len = query.chars().count();
+        match self.matching {
+            NameMatching::Fuzzy => self.matcher.fuzzy_indices(normalized, query)
+                .filter(|(score, _)| *score >= self.min_score),
+            NameMatching::Prefix => if normalized.starts_with(query) {
+                Some((0, (0..query_len).collect()))
+            } else {
+                None
+            },
+            NameMatching::Substring => normalized.find(query).map(|position| {
+                let start = normalized[..position].chars().count();
+                (-(position as i64), (start..start + query_len).collect())
+            }),
+        }
+    }
+}
+
+/// Strips diacritics and case so that, e.g., "jose" matches "José"
+fn normalize_for_matching(text: &str) -> String {
+    normalize_with_origins(text).0
+}
+
+/// Normalizes the text like `normalize_for_matching`, one character at a time, so as to also give
+/// the index of the character of the text that each character of the result came from
+fn normalize_with_origins(text: &str) -> (String, Vec<usize>) {
+    let mut normalized = String::new();
+    let mut origins = vec![];
+    for (i, ch) in text.chars().enumerate() {
+        let piece = deunicode(&ch.to_string()).to_lowercase();
+        origins.extend(piece.chars().map(|_| i));
+        normalized.push_str(&piece);
+    }
+    (normalized, origins)
 }
 
 trait Named {
@@ -84,66 +623,200 @@ fn build_main_menu(students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, pi
     let categories_for_recording = Rc::clone(&categories);
     let recorder_for_recording = Arc::clone(&event_recorder);
     let recorder_for_summary = Arc::clone(&event_recorder);
+    let recorder_for_refresh = Arc::clone(&event_recorder);
     let students_for_redeeming = Rc::clone(&students);
     let categories_for_redeeming = Rc::clone(&categories);
     let picker_for_redeeming = Arc::clone(&picker);
     let recorder_for_redeeming = Arc::clone(&event_recorder);
+    let students_for_importing = Rc::clone(&students);
+    let categories_for_importing = Rc::clone(&categories);
+    let picker_for_importing = Arc::clone(&picker);
+    let recorder_for_importing = Arc::clone(&event_recorder);
+    let students_for_listing = Rc::clone(&students);
+    let categories_for_listing = Rc::clone(&categories);
+    let picker_for_listing = Arc::clone(&picker);
+    let recorder_for_listing = Arc::clone(&event_recorder);
+    let students_for_categories = Rc::clone(&students);
+    let categories_for_categories = Rc::clone(&categories);
+    let picker_for_categories = Arc::clone(&picker);
+    let recorder_for_categories = Arc::clone(&event_recorder);
+    let recorder_for_backup = Arc::clone(&event_recorder);
+    let today_stats = match event_recorder.lock().unwrap().get_today_stats() {
+        Ok((satisfactory, participants)) => format!("Today: {} satisfactory, {} students", satisfactory, participants),
+        Err(_) => "Today: stats unavailable".to_string()
+    };
+    let next_up = format_next_up(&mut picker.lock().unwrap());
+    let read_only = event_recorder.lock().unwrap().read_only();
+    // a quick check that a roster import did what was expected
+    let loaded = match event_recorder.lock().unwrap().dropped_count() {
+        Ok(dropped) => format!("Loaded: {} enrolled, {} dropped, {} categories", students.len(), dropped, categories.len()),
+        Err(_) => format!("Loaded: {} enrolled, {} categories", students.len(), categories.len()),
+    };
+    let connected_to = {
+        let mut recorder = event_recorder.lock().unwrap();
+        let connected_to = format!("Connected to {}, schema {}", recorder.connection_settings().describe(), recorder.schema());
+        // the term is only there to tell courses apart, so it is left out if it cannot be read
+        match recorder.term_label() {
+            Ok(Some(term_label)) => format!("{}\nTerm: {}", connected_to, term_label),
+            _ => connected_to,
+        }
+    };
     Dialog::around(
         LinearLayout::vertical()
+        .child(
+            // make it obvious that nothing done in a demo is kept
+            HideableView::new(TextView::new("READ-ONLY SESSION: nothing will be saved"))
+            .with(|v| v.set_visible(read_only))
+        )
+        .child(
+            TextView::new(loaded)
+        )
+        .child(
+            TextView::new(today_stats)
+        )
+        .child(
+            TextView::new(next_up)
+            .with_name("next_up")
+        )
+        .child(DummyView)
         .child(
             Button::new("Record Participation", move |siv: &mut Cursive| {
                 siv.pop_layer();
+                let name_matching = current_name_matching(siv);
+                let labels = current_labels(siv);
                 siv.add_layer(build_recording_dialog(
                     Rc::clone(&students_for_recording),
                     Rc::clone(&categories_for_recording),
                     Arc::clone(&picker),
                     Arc::clone(&recorder_for_recording),
+                    name_matching,
+                    &labels,
                     "Ready"
-                ))
+                ));
+                show_recording_session(siv);
             })
         )
         .child(
             Button::new("Export Summary", move |siv: &mut Cursive| {
                 let recorder_for_summary = Arc::clone(&recorder_for_summary);
+                let summary_last_updated = match recorder_for_summary.lock().unwrap().get_summary_last_updated() {
+                    Ok(Some(t)) => format!("{}", t.format("%H:%M %F")),
+                    _ => "unknown".to_string()
+                };
+                let export_path = last_export_path(recorder_for_summary.lock().unwrap().schema());
+                // the picker is only offered once a roster has given sections
+                let sections = recorder_for_summary.lock().unwrap().sections().unwrap_or_default();
                 siv.add_layer(Dialog::around(
                     LinearLayout::vertical()
+                    .child(
+                        TextView::new(format!("Summary last updated: {}", summary_last_updated))
+                    )
                     .child(
                         TextView::new("Choose output filename and location:")
                     )
                     .child(
                         EditView::new()
-                        .content("data/participation_points.tsv")
+                        .content(export_path)
                         .on_submit(|siv: &mut Cursive, _: &str| {
                             siv.focus_name("exporting_submit_button").unwrap();
                         })
                         .with_name("exporting_edit")
                     )
+                    .child(
+                        SelectView::<ExportKind>::new()
+                        .popup()
+                        .item("Points per period (for UBLearns)", ExportKind::Periods)
+                        .item("Points per period, including dropped students", ExportKind::PeriodsWithDropped)
+                        .item("Detailed by category (for review)", ExportKind::Detailed)
+                        .item("Points between dates", ExportKind::DateRange)
+                        .item("Students below the minimum points", ExportKind::AtRisk)
+                        .with_name("exporting_contents_select")
+                    )
+                    .with(|layout| {
+                        if !sections.is_empty() {
+                            layout.add_child(
+                                SelectView::<Option<String>>::new()
+                                .popup()
+                                .item("All sections", None)
+                                .with_all(sections.iter().map(|section| (format!("Section {}", section), Some(section.clone()))))
+                                .with_name("exporting_section_select")
+                            );
+                        }
+                    })
+                    .child(
+                        LinearLayout::horizontal()
+                        .child(TextView::new("Dates: "))
+                        .child(
+                            EditView::new()
+                            .content(format!("{}", Local::today().format(EXPORT_DATE_FORMAT)))
+                            .with_name("exporting_from_edit")
+                            .fixed_width(12)
+                        )
+                        .child(TextView::new(" to "))
+                        .child(
+                            EditView::new()
+                            .content(format!("{}", Local::today().format(EXPORT_DATE_FORMAT)))
+                            .with_name("exporting_to_edit")
+                            .fixed_width(12)
+                        )
+                    )
+                    .child(
+                        SelectView::<ExportEncoding>::new()
+                        .popup()
+                        .item("UTF-8", ExportEncoding::Utf8)
+                        .item("UTF-16 (like Blackboard rosters)", ExportEncoding::Utf16Le)
+                        .with_name("exporting_encoding_select")
+                    )
                     .child(
                         Button::new("Submit", move |inner_siv: &mut Cursive| {
                             let chosen = inner_siv.call_on_name("exporting_edit", |v: &mut EditView| {
                                 v.get_content()
                             }).unwrap();
-                            match File::create(&*chosen) {
-                                Ok(mut outfile) => {
-                                    inner_siv.pop_layer();
-                                    inner_siv.add_layer(Dialog::around(TextView::new("Starting export").with_name("export_msg")).dismiss_button("Ok"));
-                                    match recorder_for_summary.lock().unwrap().get_summary() {
-                                        Ok(rows) => {
-                                            match export_summary(rows, &mut outfile) {
-                                                Ok(()) => { display_export_msg(inner_siv, &*format!("Finished export:\n{}", chosen)); },
-                                                Err(e) => { display_export_msg(inner_siv, &*format!("File error: {}", e)); }
-                                            }
-                                        },
-                                        Err(e) => {
-                                            display_export_msg(inner_siv, &*format!("Database error: {}", e));
-                                        }
+                            let kind = inner_siv.call_on_name("exporting_contents_select", |v: &mut SelectView<ExportKind>| {
+                                v.selection().map_or(ExportKind::Periods, |k| *k)
+                            }).unwrap();
+                            let contents = match kind {
+                                ExportKind::Periods => ExportContents::Periods,
+                                ExportKind::PeriodsWithDropped => ExportContents::PeriodsWithDropped,
+                                ExportKind::Detailed => ExportContents::Detailed,
+                                ExportKind::DateRange => match read_export_dates(inner_siv) {
+                                    Ok((first, last)) => ExportContents::DateRange(first, last),
+                                    Err(msg) => {
+                                        inner_siv.call_on_name("exporting_status_msg", |v: &mut TextView| {
+                                            v.set_content(msg)
+                                        });
+                                        return;
                                     }
                                 },
-                                Err(e) => {
-                                    inner_siv.call_on_name("exporting_status_msg", |v: &mut TextView| {
-                                        v.set_content(format!("File opening error: {:?}", e))
-                                    });
-                                }
+                                // the minimum is the one last chosen in the at-risk listing
+                                ExportKind::AtRisk => ExportContents::AtRisk(current_min_points(inner_siv)),
+                            };
+                            let encoding = inner_siv.call_on_name("exporting_encoding_select", |v: &mut SelectView<ExportEncoding>| {
+                                v.selection().map_or(ExportEncoding::Utf8, |e| *e)
+                            }).unwrap();
+                            let section = inner_siv.call_on_name("exporting_section_select", |v: &mut SelectView<Option<String>>| {
+                                v.selection().and_then(|s| (*s).clone())
+                            }).flatten();
+                            let per_period = contents == ExportContents::Periods || contents == ExportContents::PeriodsWithDropped;
+                            if section.is_some() && !per_period {
+                                inner_siv.call_on_name("exporting_status_msg", |v: &mut TextView| {
+                                    v.set_content("Only points per period can be limited to a section")
+                                });
+                                return;
+                            }
+                            if Path::new(&*chosen).exists() {
+                                // confirm before clobbering an earlier export
+                                let recorder_for_overwrite = Arc::clone(&recorder_for_summary);
+                                inner_siv.add_layer(
+                                    Dialog::text(format!("Overwrite {}?", chosen))
+                                    .button("Overwrite", move |confirm_siv: &mut Cursive| {
+                                        confirm_siv.pop_layer();
+                                        export_to(confirm_siv, &chosen, &recorder_for_overwrite, contents, encoding, section.as_deref());
+                                    })
+                                    .dismiss_button("Cancel")
+                                );
+                            } else {
+                                export_to(inner_siv, &chosen, &recorder_for_summary, contents, encoding, section.as_deref());
                             }
                         })
                         .with_name("exporting_submit_button")
@@ -155,169 +828,955 @@ fn build_main_menu(students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, pi
                 ))
             })
         )
+        .child(
+            Button::new("Refresh Summary", move |siv: &mut Cursive| {
+                let recorder_for_refresh = Arc::clone(&recorder_for_refresh);
+                siv.add_layer(Dialog::around(TextView::new("Updating...")));
+                // let the message draw before blocking on the database
+                siv.cb_sink().send(Box::new(move |inner_siv: &mut Cursive| {
+                    let msg = match recorder_for_refresh.lock().unwrap().refresh_summary() {
+                        Ok(()) => "Summary refreshed".to_string(),
+                        Err(e) => format!("Database error: {}", e),
+                    };
+                    inner_siv.pop_layer();
+                    inner_siv.add_layer(Dialog::around(TextView::new(msg)).dismiss_button("Ok"));
+                })).unwrap();
+            })
+            .with_enabled(!read_only)
+        )
         .child(
             Button::new("Redeem Points", move |siv: &mut Cursive| {
                 siv.pop_layer();
+                let name_matching = current_name_matching(siv);
                 siv.add_layer(build_redeeming_dialog_input(
                     Rc::clone(&students_for_redeeming),
                     Rc::clone(&categories_for_redeeming),
                     Arc::clone(&picker_for_redeeming),
                     Arc::clone(&recorder_for_redeeming),
+                    name_matching,
+                ));
+            })
+        )
+        .child(
+            Button::new("Students", move |siv: &mut Cursive| {
+                siv.add_layer(build_students_dialog(
+                    Rc::clone(&students_for_listing),
+                    Rc::clone(&categories_for_listing),
+                    Arc::clone(&picker_for_listing),
+                    Arc::clone(&recorder_for_listing)
+                ));
+            })
+        )
+        .child(
+            Button::new("Categories", move |siv: &mut Cursive| {
+                siv.pop_layer();
+                siv.add_layer(build_categories_dialog(
+                    Rc::clone(&students_for_categories),
+                    Rc::clone(&categories_for_categories),
+                    Arc::clone(&picker_for_categories),
+                    Arc::clone(&recorder_for_categories),
+                    "Ready"
+                ));
+            })
+        )
+        .child(
+            Button::new("Backup", move |siv: &mut Cursive| {
+                let recorder_for_backup = Arc::clone(&recorder_for_backup);
+                siv.add_layer(Dialog::around(TextView::new("Backing up...")));
+                // let the message draw before blocking on the database
+                siv.cb_sink().send(Box::new(move |inner_siv: &mut Cursive| {
+                    let mut recorder = recorder_for_backup.lock().unwrap();
+                    let path = PathBuf::from(format!("data/backup_{}_{}.sql", recorder.schema(), Local::now().format("%Y%m%d-%H%M%S")));
+                    let msg = match recorder.backup(&path) {
+                        Ok(count) => format!("Backed up {} rows to\n{}", count, path.display()),
+                        Err(e) => format!("Backup failed: {}", e),
+                    };
+                    drop(recorder);
+                    inner_siv.pop_layer();
+                    inner_siv.add_layer(Dialog::around(TextView::new(msg)).dismiss_button("Ok"));
+                })).unwrap();
+            })
+        )
+        .child(
+            Button::new("Import Roster", move |siv: &mut Cursive| {
+                siv.pop_layer();
+                siv.add_layer(build_import_dialog(
+                    Rc::clone(&students_for_importing),
+                    Rc::clone(&categories_for_importing),
+                    Arc::clone(&picker_for_importing),
+                    Arc::clone(&recorder_for_importing),
                 ));
             })
+            .with_enabled(!read_only)
         )
         .child(
-            Button::new("Quit", Cursive::quit)
+            Button::new("Quit", quit)
+        )
+        .child(DummyView)
+        .child(
+            // shows which database is in use when troubleshooting
+            TextView::new(connected_to)
         )
     )
 }
 
-fn build_recording_dialog(students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>, status_msg: &str) -> Dialog {
+fn build_students_dialog(students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>) -> Dialog {
+    let initial_listing = format_student_listing(&event_recorder, StudentSort::Name);
+    let recorder_for_merge = Arc::clone(&event_recorder);
+    let recorder_for_at_risk = Arc::clone(&event_recorder);
+    let read_only = event_recorder.lock().unwrap().read_only();
     Dialog::around(
         LinearLayout::vertical()
         .child(
             LinearLayout::horizontal()
+            .child(TextView::new("Sort by: "))
             .child(
-                build_recording_student_selector(Rc::clone(&students), Arc::clone(&picker))
-            )
-            .child(
-                build_category_selector(Rc::clone(&categories))
-            )
-            .child(
-                build_satisfactory_selector()
+                SelectView::<StudentSort>::new()
+                .popup()
+                .item("name", StudentSort::Name)
+                .item("fewest points", StudentSort::Points)
+                .item("most recently active", StudentSort::LastActive)
+                .on_submit(move |siv: &mut Cursive, sort: &StudentSort| {
+                    let listing = format_student_listing(&event_recorder, *sort);
+                    siv.call_on_name("students_listing", |v: &mut TextView| {
+                        v.set_content(listing);
+                    });
+                })
             )
-            .child(
-                build_recording_buttons_column(
+        )
+        .child(DummyView)
+        .child(
+            TextView::new(initial_listing)
+            .with_name("students_listing")
+            .scrollable()
+        )
+    )
+    .title("Students")
+    .with(|dialog| {
+        // merging changes events, so it is left out of read-only sessions
+        if !read_only {
+            dialog.add_button("Merge duplicates", move |siv: &mut Cursive| {
+                siv.add_layer(build_student_merge_dialog(
                     Rc::clone(&students),
                     Rc::clone(&categories),
                     Arc::clone(&picker),
-                    Arc::clone(&event_recorder)
-                )
+                    Arc::clone(&recorder_for_merge)
+                ));
+            });
+        }
+    })
+    .button("At risk", move |siv: &mut Cursive| {
+        let min_points = current_min_points(siv);
+        siv.add_layer(build_at_risk_dialog(Arc::clone(&recorder_for_at_risk), min_points));
+    })
+    .dismiss_button("Back")
+}
+
+/// Builds a dialog listing enrolled students with fewer points than a minimum, for advisors
+/// following up on students at risk of a low participation grade
+fn build_at_risk_dialog(event_recorder: Arc<Mutex<EventRecorder>>, min_points: i32) -> Dialog {
+    let initial_listing = format_at_risk_listing(&event_recorder, min_points);
+    let recorder_for_submit = Arc::clone(&event_recorder);
+    Dialog::around(
+        LinearLayout::vertical()
+        .child(
+            LinearLayout::horizontal()
+            .child(TextView::new("Fewer than "))
+            .child(
+                EditView::new()
+                .content(format!("{}", min_points))
+                .on_submit(move |siv: &mut Cursive, _: &str| {
+                    list_at_risk(siv, &recorder_for_submit);
+                })
+                .with_name("at_risk_min_edit")
+                .fixed_width(6)
             )
+            .child(TextView::new(" points"))
         )
+        .child(DummyView)
         .child(
-            TextView::new(status_msg)
-            .with_name("recording_status")
+            TextView::new(initial_listing)
+            .with_name("at_risk_listing")
+            .scrollable()
         )
     )
-    .title("Event Recorder")
+    .title("At Risk")
+    .button("List", move |siv: &mut Cursive| {
+        list_at_risk(siv, &event_recorder);
+    })
+    .dismiss_button("Back")
 }
 
-fn build_recording_student_selector(students: Rc<Vec<Student>>, picker: Arc<Mutex<StudentPicker>>) -> PaddedView<ResizedView<LinearLayout>> {
-    let student_finder = Rc::new(NamedFinder::new(Rc::clone(&students)));
-    let picker = Arc::clone(&picker);
-    let students_for_on_edit = Rc::clone(&students);
-    let student_finder_for_on_edit = Rc::clone(&student_finder);
-    let students_for_on_submit = Rc::clone(&students);
-    PaddedView::lrtb(
-        2, 2, 0, 0,
+/// Lists the students below the minimum entered in the at-risk dialog, which is also remembered
+/// for exporting them
+fn list_at_risk(siv: &mut Cursive, event_recorder: &Arc<Mutex<EventRecorder>>) {
+    let text = siv.find_name::<EditView>("at_risk_min_edit").unwrap().get_content();
+    let listing = match text.trim().parse::<i32>() {
+        Ok(min_points) => {
+            siv.with_user_data(|state: &mut MenuState| {
+                state.min_points = min_points;
+            });
+            format_at_risk_listing(event_recorder, min_points)
+        },
+        Err(_) => format!("Not a number: {}", text),
+    };
+    siv.call_on_name("at_risk_listing", |v: &mut TextView| {
+        v.set_content(listing);
+    });
+}
+
+fn format_at_risk_listing(event_recorder: &Arc<Mutex<EventRecorder>>, min_points: i32) -> String {
+    match event_recorder.lock().unwrap().list_at_risk(min_points) {
+        Ok(students) if students.is_empty() => format!("No enrolled student has fewer than {} points", min_points),
+        Ok(students) => {
+            let mut lines = vec![format!("{:<30} {:<12} {:>6}", "Name", "Username", "Points")];
+            lines.extend(students.iter().map(|s| format!("{:<30} {:<12} {:>6}", s.name, s.username, s.points)));
+            lines.join("\n")
+        },
+        Err(e) => format!("Database error: {}", e),
+    }
+}
+
+/// Builds a dialog for folding a student who appears twice into their other entry, so that their
+/// events are no longer split between the two
+fn build_student_merge_dialog(students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>) -> Dialog {
+    let mut sorted_students = Vec::from_iter(students.iter());
+    sorted_students.sort_by(|a, b| a.name.cmp(&b.name));
+    // UB IDs tell the entries apart, since the duplicate usually has a different name
+    let items: Vec<(String, i32)> = sorted_students.iter()
+        .map(|s| (format!("{} ({})", s.name, s.ub_id), s.db_id))
+        .collect();
+    Dialog::around(
         LinearLayout::vertical()
         .child(
-            TextView::new("Student")
+            TextView::new("Move all events of:")
         )
         .child(
-            EditView::new()
-            // update results every time the query changes
-            .on_edit(move |siv: &mut Cursive, query: &str, _cursor: usize| {
-                if query.len() > 1 && students_for_on_edit.iter().find(|s| s.name == query[0..query.len()-1]).is_some() {
-                    // assume that user wants to change selection
-                    let query = &query[query.len()-1..];
-                    siv.call_on_name("recording_student_query", |v: &mut EditView| {
-                        v.set_content(query.to_string());
-                    });
-                    let matches = student_finder_for_on_edit.find(query);
-                    // Update the `matches` view with the filtered array of student names
-                    siv.call_on_name("recording_student_matches", |v: &mut SelectView| {
-                        v.clear();
-                        v.add_all_str(matches.iter().map(|s| s.name.to_string()));
-                    });
-                } else {
-                    // update without changing query
-                    let matches = student_finder_for_on_edit.find(query);
-                    // Update the `matches` view with the filtered array of student names
-                    siv.call_on_name("recording_student_matches", |v: &mut SelectView| {
-                        v.clear();
-                        v.add_all_str(matches.iter().map(|s| s.name.to_string()));
-                    });
-                }
-                siv.call_on_name("recording_status", |v: &mut TextView| {
-                    v.set_content("Select student");
-                });
-            })
-            // if possible, select student when pressing enter on this edit view
-            .on_submit(move |siv: &mut Cursive, text: &str| {
-                if text.len() > 0 && students_for_on_submit.iter().find(|s| s.name == text).is_none() {
-                    // try to get the top matching student
-                    let choice = siv.call_on_name("recording_student_matches", |v: &mut SelectView| {
-                        match v.get_item(0) {
-                            Some((name, _)) => name.to_string(),
-                            None => "".to_string()
-                        }
-                    }).unwrap();
-                    if choice.len() > 0 {
-                        siv.call_on_name("recording_student_query", |v: &mut EditView| {
-                            v.set_content(choice);
-                        });
-                        // move focus to next column
-                        siv.focus_name("category_edit").unwrap();
-                        siv.call_on_name("recording_status", |v: &mut TextView| {
-                            v.set_content("Select category");
-                        });
-                    } else {
-                        siv.call_on_name("recording_status", |v: &mut TextView| {
-                            v.set_content("No matching student; try again");
-                        });
-                    }
-                } else {
-                    // choose a random student
-                    let choice_for_edit_view = match picker.lock().unwrap().next() {
-                        Some(student) => student.name,
-                        None => "".to_string()
-                    };
-                    let choice_for_select_view = choice_for_edit_view.clone();
-                    // Update the `matches` view with random student
-                    siv.call_on_name("recording_student_matches", |v: &mut SelectView| {
-                        v.clear();
-                        v.add_item_str(choice_for_select_view);
-                    });
-                    siv.call_on_name("recording_student_query", |v: &mut EditView| {
-                        v.set_content(choice_for_edit_view);
-                    });
-                    // move focus to next column
-                    siv.focus_name("category_edit").unwrap();
-                    siv.call_on_name("recording_status", |v: &mut TextView| {
-                        v.set_content("Select category");
-                    });
-                }
-            })
-            .with_name("recording_student_query")
+            SelectView::<i32>::new()
+            .popup()
+            .with_all(items.clone())
+            .with_name("merge_source_select")
         )
-        // search results below the input
         .child(
-            SelectView::<String>::new()
-                // show only top match
-                .popup()
-                // no students by default
-                // freezes popup, passing view on tab (but updates top name still)
-                .disabled()
-                .with_name("recording_student_matches"),
+            TextView::new("to the student whose UB ID is kept:")
+        )
+        .child(
+            SelectView::<i32>::new()
+            .popup()
+            .with_all(items)
+            .with_name("merge_target_select")
+        )
+        .child(DummyView)
+        .child(
+            TextView::new("The first student is then marked dropped.")
+        )
+        .child(
+            TextView::new("Ready")
+            .with_name("merge_status")
+        )
+    )
+    .title("Merge Students")
+    .button("Merge", move |siv: &mut Cursive| {
+        let source_id = siv.find_name::<SelectView<i32>>("merge_source_select").unwrap().selection().map(|id| *id);
+        let target_id = siv.find_name::<SelectView<i32>>("merge_target_select").unwrap().selection().map(|id| *id);
+        let (source, target) = match (source_id, target_id) {
+            (Some(s), Some(t)) if s != t => (
+                students.iter().find(|st| st.db_id == s).unwrap().clone(),
+                students.iter().find(|st| st.db_id == t).unwrap().clone(),
+            ),
+            _ => {
+                siv.call_on_name("merge_status", |v: &mut TextView| {
+                    v.set_content("Choose two different students");
+                });
+                return;
+            }
+        };
+        let categories_for_confirm = Rc::clone(&categories);
+        let picker_for_confirm = Arc::clone(&picker);
+        let recorder_for_confirm = Arc::clone(&event_recorder);
+        siv.add_layer(
+            Dialog::text(format!(
+                "Move all events of {} ({}) to {} ({}) and mark {} dropped?",
+                source.name, source.ub_id, target.name, target.ub_id, source.name
+            ))
+            .button("Merge", move |confirm_siv: &mut Cursive| {
+                confirm_siv.pop_layer();
+                let merged = recorder_for_confirm.lock().unwrap().merge_students(source.db_id, target.db_id);
+                match merged {
+                    Ok(students) => {
+                        let msg = format!("Merged {} into {}", source.name, target.name);
+                        let students = Rc::new(students);
+                        let picker_save_path = picker_for_confirm.lock().unwrap().save_path().map(|p| p.to_path_buf());
+                        let picker = Arc::new(Mutex::new(get_student_picker(Rc::clone(&students), picker_save_path)));
+                        set_menu_state(confirm_siv, &students, &categories_for_confirm, &picker, &recorder_for_confirm);
+                        while confirm_siv.pop_layer().is_some() {}
+                        confirm_siv.add_layer(build_main_menu(
+                            students,
+                            Rc::clone(&categories_for_confirm),
+                            picker,
+                            Arc::clone(&recorder_for_confirm)
+                        ));
+                        confirm_siv.add_layer(Dialog::info(msg));
+                    },
+                    Err(e) => {
+                        confirm_siv.call_on_name("merge_status", |v: &mut TextView| {
+                            v.set_content(format!("Database error: {}", e));
+                        });
+                    }
+                }
+            })
+            .dismiss_button("Cancel")
+        );
+    })
+    .dismiss_button("Back")
+}
+
+fn format_student_listing(event_recorder: &Arc<Mutex<EventRecorder>>, sort: StudentSort) -> String {
+    match event_recorder.lock().unwrap().list_students(sort) {
+        Ok(students) => {
+            let mut lines = vec![format!("{:<30} {:>6}  {}", "Name", "Points", "Last active")];
+            lines.extend(students.iter().map(|s| format!(
+                "{:<30} {:>6}  {}",
+                s.name,
+                s.points,
+                s.last_active.map_or("never".to_string(), |t| format!("{}", t.format("%H:%M %F")))
+            )));
+            lines.join("\n")
+        },
+        Err(e) => format!("Database error: {}", e),
+    }
+}
+
+fn build_categories_dialog(students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>, status_msg: &str) -> Dialog {
+    let students_for_rename = Rc::clone(&students);
+    let categories_for_rename = Rc::clone(&categories);
+    let picker_for_rename = Arc::clone(&picker);
+    let recorder_for_rename = Arc::clone(&event_recorder);
+    let students_for_merge = Rc::clone(&students);
+    let categories_for_merge = Rc::clone(&categories);
+    let picker_for_merge = Arc::clone(&picker);
+    let recorder_for_merge = Arc::clone(&event_recorder);
+    let read_only = event_recorder.lock().unwrap().read_only();
+    Dialog::around(
+        LinearLayout::vertical()
+        .child(
+            TextView::new("Choose a category:")
+        )
+        .child(
+            SelectView::<String>::new()
+            .with_all_str(categories.iter().map(|c| c.name.clone()))
+            .with_name("categories_select")
+            .scrollable()
+            .max_height(10)
+        )
+        .child(DummyView)
+        .child(
+            TextView::new("New name, or category to merge into:")
+        )
+        .child(
+            EditView::new()
+            .max_content_width(25)
+            .with_name("categories_name_edit")
+        )
+        .child(
+            TextView::new(status_msg)
+            .with_name("categories_status")
+        )
+    )
+    .title("Categories")
+    .with(|dialog| {
+        // renaming and merging change events, so they are left out of read-only sessions
+        if !read_only {
+            dialog.add_button("Rename", move |siv: &mut Cursive| {
+                let (source, target) = match read_category_change(siv) {
+                    Some(names) => names,
+                    None => return,
+                };
+                // the names are unique in the database, so renaming onto an existing name is really a merge
+                if categories_for_rename.iter().any(|c| c.name == target) {
+                    siv.call_on_name("categories_status", |v: &mut TextView| {
+                        v.set_content(format!("A category named {} already exists; merge into it instead", target));
+                    });
+                    return;
+                }
+                let renamed = recorder_for_rename.lock().unwrap().rename_category(&source, &target);
+                show_changed_categories(
+                    siv,
+                    renamed,
+                    &format!("Renamed {} to {}", source, target),
+                    &students_for_rename,
+                    &picker_for_rename,
+                    &recorder_for_rename
+                );
+            });
+            dialog.add_button("Merge into", move |siv: &mut Cursive| {
+                let (source, target) = match read_category_change(siv) {
+                    Some(names) => names,
+                    None => return,
+                };
+                if source == target || !categories_for_merge.iter().any(|c| c.name == target) {
+                    siv.call_on_name("categories_status", |v: &mut TextView| {
+                        v.set_content(format!("Enter the name of another existing category to merge {} into", source));
+                    });
+                    return;
+                }
+                let merged = recorder_for_merge.lock().unwrap().merge_categories(&source, &target);
+                show_changed_categories(
+                    siv,
+                    merged,
+                    &format!("Merged {} into {}", source, target),
+                    &students_for_merge,
+                    &picker_for_merge,
+                    &recorder_for_merge
+                );
+            });
+        }
+    })
+    .button("Back to main", move |siv: &mut Cursive| {
+        siv.pop_layer();
+        siv.add_layer(build_main_menu(
+            Rc::clone(&students),
+            Rc::clone(&categories),
+            Arc::clone(&picker),
+            Arc::clone(&event_recorder)))
+    })
+}
+
+/// Reads the chosen category and the name typed in the categories dialog, reporting what is
+/// missing if either is not given
+fn read_category_change(siv: &mut Cursive) -> Option<(String, String)> {
+    let source = siv.find_name::<SelectView<String>>("categories_select").unwrap()
+        .selection()
+        .map(|name| name.to_string());
+    let target = siv.find_name::<EditView>("categories_name_edit").unwrap()
+        .get_content()
+        .trim()
+        .to_string();
+    match source {
+        Some(source) if !target.is_empty() => Some((source, target)),
+        Some(_) => {
+            siv.call_on_name("categories_status", |v: &mut TextView| {
+                v.set_content("Enter a category name");
+            });
+            None
+        },
+        None => {
+            siv.call_on_name("categories_status", |v: &mut TextView| {
+                v.set_content("No category chosen");
+            });
+            None
+        }
+    }
+}
+
+/// Reopens the categories dialog with the changed categories, which are also what the main menu
+/// and recording dialogs will be built from from now on
+fn show_changed_categories(siv: &mut Cursive, changed: Result<Vec<Category>, AppError>, msg: &str, students: &Rc<Vec<Student>>, picker: &Arc<Mutex<StudentPicker>>, event_recorder: &Arc<Mutex<EventRecorder>>) {
+    match changed {
+        Ok(categories) => {
+            let categories = Rc::new(categories);
+            set_menu_state(siv, students, &categories, picker, event_recorder);
+            siv.pop_layer();
+            siv.add_layer(build_categories_dialog(
+                Rc::clone(students),
+                categories,
+                Arc::clone(picker),
+                Arc::clone(event_recorder),
+                msg
+            ));
+        },
+        Err(e) => {
+            siv.call_on_name("categories_status", |v: &mut TextView| {
+                v.set_content(format!("Database error: {}", e));
+            });
+        }
+    }
+}
+
+fn build_import_dialog(students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>) -> Dialog {
+    let have_students = !students.is_empty();
+    let recorder_for_import = Arc::clone(&event_recorder);
+    let picker_save_path = picker.lock().unwrap().save_path().map(|p| p.to_path_buf());
+    let prompt = if have_students {
+        "Choose roster file to import:"
+    } else {
+        "No students in database; choose roster file to import:"
+    };
+    let leave_button = if have_students {
+        Button::new("Back to main", move |siv: &mut Cursive| {
+            siv.pop_layer();
+            siv.add_layer(build_main_menu(
+                Rc::clone(&students),
+                Rc::clone(&categories),
+                Arc::clone(&picker),
+                Arc::clone(&event_recorder)))
+        })
+    } else {
+        Button::new("Quit", quit)
+    };
+    Dialog::around(
+        LinearLayout::vertical()
+        .child(
+            TextView::new(prompt)
+        )
+        .child(
+            EditView::new()
+            .on_submit(|siv: &mut Cursive, _: &str| {
+                siv.focus_name("import_submit_button").unwrap();
+            })
+            .with_name("import_edit")
+        )
+        .child(
+            LinearLayout::horizontal()
+            .child(TextView::new("Format: "))
+            .child(
+                SelectView::<RosterFormat>::new()
+                .popup()
+                .item("Blackboard (UTF-16, tab separated)", RosterFormat::Blackboard)
+                .item("Canvas (CSV with header row)", RosterFormat::Canvas)
+                .with_name("import_format_select")
+            )
+        )
+        .child(
+            Button::new("Import", move |siv: &mut Cursive| {
+                let chosen = siv.call_on_name("import_edit", |v: &mut EditView| {
+                    v.get_content()
+                }).unwrap();
+                let format = siv.call_on_name("import_format_select", |v: &mut SelectView<RosterFormat>| {
+                    v.selection().map_or(RosterFormat::Blackboard, |f| *f)
+                }).unwrap();
+                let roster = match read_roster(OsString::from(&*chosen), format) {
+                    Ok(r) => r,
+                    Err(e) => {
+                        siv.call_on_name("import_status_msg", |v: &mut TextView| {
+                            v.set_content(format!("Error in reading roster: {}", e))
+                        });
+                        return;
+                    }
+                };
+                if roster.is_empty() {
+                    siv.call_on_name("import_status_msg", |v: &mut TextView| {
+                        v.set_content("No students found in roster; try again")
+                    });
+                    return;
+                }
+                let imported_msg = format!("Imported {}", roster);
+                let imported = recorder_for_import.lock().unwrap().import_roster(roster);
+                match imported {
+                    Ok((students, categories, disambiguated)) => {
+                        if students.is_empty() {
+                            siv.call_on_name("import_status_msg", |v: &mut TextView| {
+                                v.set_content("No students found in roster; try again")
+                            });
+                        } else {
+                            // the previous students and picker are replaced wholesale, so nothing
+                            // built from them outlives this dialog
+                            let students = Rc::new(students);
+                            let categories = Rc::new(categories);
+                            let picker = Arc::new(Mutex::new(get_student_picker(Rc::clone(&students), picker_save_path.clone())));
+                            set_menu_state(siv, &students, &categories, &picker, &recorder_for_import);
+                            siv.pop_layer();
+                            siv.add_layer(build_main_menu(
+                                students,
+                                categories,
+                                picker,
+                                Arc::clone(&recorder_for_import)
+                            ));
+                            let imported_msg = if disambiguated.is_empty() {
+                                imported_msg
+                            } else {
+                                format!("{}\nStudents sharing a name were told apart by username:\n{}", imported_msg, disambiguated.join("\n"))
+                            };
+                            siv.add_layer(Dialog::info(imported_msg));
+                        }
+                    },
+                    Err(e) => {
+                        siv.call_on_name("import_status_msg", |v: &mut TextView| {
+                            v.set_content(format!("Database error: {}", e))
+                        });
+                    }
+                }
+            })
+            .with_name("import_submit_button")
+        )
+        .child(
+            TextView::new("Ready")
+            .with_name("import_status_msg")
+        )
+        .child(
+            leave_button
+        )
+    )
+    .title("Import Roster")
+}
+
+fn build_recording_dialog(students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>, name_matching: NameMatching, labels: &Labels, status_msg: &str) -> Dialog {
+    let default_satisfaction = event_recorder.lock().unwrap().default_satisfaction();
+    let read_only = event_recorder.lock().unwrap().read_only();
+    // the counts are only a reminder, so recording goes on without them if they cannot be read
+    let today_counts = event_recorder.lock().unwrap().today_category_counts().unwrap_or_default();
+    let category_order = event_recorder.lock().unwrap().category_order().to_vec();
+    Dialog::around(
+        LinearLayout::vertical()
+        .child(
+            LinearLayout::horizontal()
+            .child(
+                build_recording_student_selector(
+                    Rc::clone(&students),
+                    Rc::clone(&categories),
+                    Arc::clone(&picker),
+                    Arc::clone(&event_recorder),
+                    name_matching
+                )
+            )
+            .child(
+                build_category_selector(Rc::clone(&categories), event_recorder.lock().unwrap().default_category(), &category_order, name_matching, &today_counts)
+            )
+            .child(
+                build_satisfactory_selector(default_satisfaction, &labels.satisfactory_header)
+            )
+            .child(
+                build_recording_buttons_column(
+                    Rc::clone(&students),
+                    Rc::clone(&categories),
+                    Arc::clone(&picker),
+                    Arc::clone(&event_recorder),
+                    read_only
+                )
+            )
+            .child(
+                build_session_tally_column()
+            )
+        )
+        .child(
+            build_recording_when_row()
+        )
+        .child(
+            TextView::new(status_msg)
+            .with_name("recording_status")
+        )
+    )
+    .title(if read_only { "Event Recorder (read-only)" } else { "Event Recorder" })
+}
+
+/// Row of the recording dialog for entering when a back-filled event happened, shown only in
+/// advanced mode
+type RecordingWhenRow = HideableView<PaddedView<LinearLayout>>;
+
+/// Format in which the time of a back-filled event is entered
+const RECORDING_WHEN_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+fn build_recording_when_row() -> NamedView<RecordingWhenRow> {
+    HideableView::new(
+        PaddedView::lrtb(
+            2, 2, 1, 0,
+            LinearLayout::horizontal()
+            .child(
+                TextView::new("Happened at (YYYY-MM-DD HH:MM): ")
+            )
+            .child(
+                EditView::new()
+                .on_submit(|siv: &mut Cursive, _: &str| {
+                    siv.focus_name("recording_submit_button").unwrap();
+                })
+                .with_name("recording_when_edit")
+                .fixed_width(18)
+            )
+        )
+    )
+    .hidden()
+    .with_name("recording_when_row")
+}
+
+/// Shows the advanced row of the recording dialog, filled in with the given time
+fn show_recording_when(siv: &mut Cursive, when: DateTime<Local>) {
+    siv.call_on_name("recording_when_row", |v: &mut RecordingWhenRow| {
+        v.unhide();
+    });
+    siv.call_on_name("recording_when_edit", |v: &mut EditView| {
+        v.set_content(format!("{}", when.format(RECORDING_WHEN_FORMAT)));
+    });
+}
+
+/// Says what was just recorded and when it was stored as having happened, so that it can be
+/// checked at a glance; a back-filled event says it was dated so that the next one is not misdated
+/// by accident
+fn submitted_msg(student_name: &str, category_names: &[String], satisfactory: Satisfaction, labels: &Labels, recorded_at: DateTime<Local>, back_filled: bool) -> String {
+    let time = if back_filled {
+        format!("dated {}", recorded_at.format(RECORDING_WHEN_FORMAT))
+    } else {
+        format!("at {}", recorded_at.format("%H:%M:%S"))
+    };
+    format!("Submitted {}, {}, {}: {}, {}", student_name, category_names.join(", "), labels.satisfactory_prompt, satisfactory, time)
+}
+
+/// Reads when the event being recorded happened; `None` means now, which is the case unless the
+/// advanced row is showing
+fn read_recording_when(siv: &mut Cursive) -> Result<Option<DateTime<Local>>, String> {
+    let advanced = siv.find_name::<RecordingWhenRow>("recording_when_row").unwrap().is_visible();
+    if !advanced {
+        return Ok(None);
+    }
+    let text = siv.find_name::<EditView>("recording_when_edit").unwrap().get_content();
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+    let naive = NaiveDateTime::parse_from_str(text.trim(), RECORDING_WHEN_FORMAT)
+        .map_err(|_| format!("Could not read \"{}\" as a time; use YYYY-MM-DD HH:MM", text))?;
+    let when = Local.from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| format!("{} does not exist in the local time zone", text))?;
+    if when > Local::now() {
+        return Err(format!("{} is in the future", text));
+    }
+    Ok(Some(when))
+}
+
+/// Switches the recording dialog between recording as of yesterday's class and as of now
+fn toggle_recording_yesterday(siv: &mut Cursive) {
+    let class_time = current_class_time(siv);
+    let yesterday = match Local::today().pred().and_time(class_time) {
+        Some(when) => when,
+        None => {
+            siv.call_on_name("recording_status", |v: &mut TextView| {
+                v.set_content(format!("Yesterday at {} does not exist in the local time zone", class_time.format("%H:%M")));
+            });
+            return;
+        }
+    };
+    let yesterday_text = format!("{}", yesterday.format(RECORDING_WHEN_FORMAT));
+    let advanced = siv.find_name::<RecordingWhenRow>("recording_when_row").unwrap().is_visible();
+    let when_text = siv.find_name::<EditView>("recording_when_edit").unwrap().get_content();
+    if advanced && *when_text == yesterday_text {
+        siv.call_on_name("recording_when_row", |v: &mut RecordingWhenRow| {
+            v.hide();
+        });
+        siv.call_on_name("recording_status", |v: &mut TextView| {
+            v.set_content("Recording as of now");
+        });
+    } else {
+        show_recording_when(siv, yesterday);
+        siv.call_on_name("recording_status", |v: &mut TextView| {
+            v.set_content(format!("RECORDING FOR YESTERDAY ({}); press \"Yesterday\" again to record as of now", yesterday_text));
+        });
+    }
+}
+
+fn build_recording_student_selector(students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>, name_matching: NameMatching) -> PaddedView<ResizedView<LinearLayout>> {
+    let student_finder = Rc::new(NamedFinder::new(Rc::clone(&students), name_matching));
+    let students_for_quick = Rc::clone(&students);
+    let picker_for_quick = Arc::clone(&picker);
+    let student_finder_for_on_edit = Rc::clone(&student_finder);
+    let student_finder_for_on_submit = Rc::clone(&student_finder);
+    // the last name submitted without any match, so that submitting it again can be told apart
+    let unmatched_for_on_submit: Rc<RefCell<Option<String>>> = Rc::new(RefCell::new(None));
+    PaddedView::lrtb(
+        2, 2, 0, 0,
+        LinearLayout::vertical()
+        .child(
+            TextView::new("Student")
+        )
+        .child(
+            OnEventView::new(
+                EditView::new()
+                // update results every time the query changes
+                .on_edit(move |siv: &mut Cursive, query: &str, _cursor: usize| {
+                    if query.len() > 1 && student_finder_for_on_edit.exact(&query[0..query.len()-1]).is_some() {
+                        // assume that user wants to change selection
+                        let query = &query[query.len()-1..];
+                        siv.call_on_name("recording_student_query", |v: &mut EditView| {
+                            v.set_content(query.to_string());
+                        });
+                        let matches = student_finder_for_on_edit.find_with_indices(query);
+                        show_recording_student_matches(siv, &matches);
+                    } else {
+                        // update without changing query
+                        let matches = student_finder_for_on_edit.find_with_indices(query);
+                        show_recording_student_matches(siv, &matches);
+                    }
+                    siv.call_on_name("recording_status", |v: &mut TextView| {
+                        v.set_content("Select student");
+                    });
+                })
+                // if possible, select student when pressing enter on this edit view
+                .on_submit(move |siv: &mut Cursive, text: &str| {
+                    if !advance_allowed(siv) {
+                        return;
+                    }
+                    if text.len() > 0 && student_finder_for_on_submit.exact(text).is_none() {
+                        // try to get the top matching student
+                        let choice = siv.call_on_name("recording_student_matches", |v: &mut SelectView| {
+                            match v.get_item(0) {
+                                Some((_, name)) => name.to_string(),
+                                None => "".to_string()
+                            }
+                        }).unwrap();
+                        if choice.len() > 0 {
+                            unmatched_for_on_submit.borrow_mut().take();
+                            siv.call_on_name("recording_student_query", |v: &mut EditView| {
+                                v.set_content(choice);
+                            });
+                            let prompt = focus_after_student(siv);
+                            siv.call_on_name("recording_status", |v: &mut TextView| {
+                                v.set_content(prompt);
+                            });
+                        } else if unmatched_for_on_submit.borrow().as_deref() == Some(text) {
+                            // pressing enter again on the same unmatched name asks for a random student
+                            unmatched_for_on_submit.borrow_mut().take();
+                            choose_random_student(siv, &picker);
+                        } else {
+                            // stay in the query so that a mistyped name can be fixed
+                            *unmatched_for_on_submit.borrow_mut() = Some(text.to_string());
+                            siv.call_on_name("recording_status", |v: &mut TextView| {
+                                v.set_content(format!("No match for \"{}\"; fix it, or press enter again for random", text));
+                            });
+                        }
+                    } else if !text.is_empty() {
+                        // the name is already exact, so keep it rather than replacing it at random
+                        let prompt = focus_after_student(siv);
+                        siv.call_on_name("recording_status", |v: &mut TextView| {
+                            v.set_content(prompt);
+                        });
+                    } else {
+                        choose_random_student(siv, &picker);
+                    }
+                })
+                .with_name("recording_student_query")
+            )
+            // records the top match as satisfactory in the default category in one keystroke
+            .on_event(Event::CtrlChar('y'), move |siv: &mut Cursive| {
+                quick_record(siv, &students_for_quick, &categories, &picker_for_quick, &event_recorder);
+            })
+        )
+        // search results below the input
+        .child(
+            SelectView::<String>::new()
+                // show only top match
+                .popup()
+                // no students by default
+                // freezes popup, passing view on tab (but updates top name still)
+                .disabled()
+                .with_name("recording_student_matches"),
         )
         .fixed_width(30),
     )
 }
 
-fn build_category_selector(categories: Rc<Vec<Category>>) -> PaddedView<LinearLayout> {
-    let categories_keeper: HashMap<String, Category> = HashMap::from_iter(categories.iter()
-        .map(|c| (c.name[0..1].to_string(), c.clone()))
-    );
-    let mut sorted_categories = Vec::from_iter(categories.iter()
-        .map(|c| c.name.to_string())
-    );
-    sorted_categories.sort();
-    let categories_sign = sorted_categories.iter()
-        .map(|c| format!("[{}]{}", &c[0..1], &c[1..]))
+/// Updates the recording dialog's matches with the students found for the typed text
+fn show_recording_student_matches(siv: &mut Cursive, matches: &[(&Student, Vec<usize>)]) {
+    let show_usernames = showing_usernames(siv);
+    let highlight = highlighting_matches(siv);
+    siv.call_on_name("recording_student_matches", |v: &mut SelectView| {
+        v.clear();
+        v.add_all(matches.iter().map(|(s, matched)| {
+            student_match_item(s, show_usernames, if highlight { matched } else { &[] })
+        }));
+    });
+}
+
+/// Fills the recording dialog with the next student from the picker
+fn choose_random_student(siv: &mut Cursive, picker: &Arc<Mutex<StudentPicker>>) {
+    let mut picker = picker.lock().unwrap();
+    let chosen = match picker.next() {
+        Some(student) => student,
+        None => {
+            siv.call_on_name("recording_status", |v: &mut TextView| {
+                v.set_content("No students qualify for the picker filter");
+            });
+            return;
+        }
+    };
+    // keep the order of the current pass in case of a restart
+    let save_result = picker.autosave();
+    drop(picker);
+    let (label, choice_for_edit_view) = student_match_item(&chosen, showing_usernames(siv), &[]);
+    // Update the `matches` view with random student
+    siv.call_on_name("recording_student_matches", |v: &mut SelectView| {
+        v.clear();
+        v.add_item(label, choice_for_edit_view.clone());
+    });
+    siv.call_on_name("recording_student_query", |v: &mut EditView| {
+        v.set_content(choice_for_edit_view);
+    });
+    let status_msg = match save_result {
+        Ok(()) => focus_after_student(siv),
+        Err(e) => {
+            focus_after_student(siv);
+            format!("Could not save picker order: {}", e)
+        }
+    };
+    siv.call_on_name("recording_status", |v: &mut TextView| {
+        v.set_content(status_msg);
+    });
+}
+
+/// Moves focus to the column after the student, skipping over the category when a default one is
+/// already filled in, and returns the prompt for that column
+fn focus_after_student(siv: &mut Cursive) -> String {
+    let have_category = !siv.find_name::<EditView>("category_edit").unwrap().get_content().is_empty();
+    if have_category {
+        siv.focus_name("satisfactory_select").unwrap();
+        current_satisfaction_prompt(siv)
+    } else {
+        siv.focus_name("category_edit").unwrap();
+        "Select category".to_string()
+    }
+}
+
+/// Puts the categories named in `order` first, in that order, followed by the rest sorted by
+/// name
+fn order_categories(categories: &[Category], order: &[String]) -> Vec<Category> {
+    let mut sorted_categories = categories.to_vec();
+    sorted_categories.sort_by_key(|c| {
+        let position = order.iter().position(|name| *name == c.name).unwrap_or(order.len());
+        (position, c.name.clone())
+    });
+    sorted_categories
+}
+
+/// Assigns each category a one-character hotkey, in the order given by `order_categories`
+///
+/// Each category gets the first character of its name unless an earlier category already took
+/// it, in which case the next untaken character of its name is used.
+fn assign_category_hotkeys(categories: &[Category], order: &[String]) -> Vec<(String, Category)> {
+    let sorted_categories = order_categories(categories, order);
+    let mut taken: Vec<String> = vec![];
+    sorted_categories.into_iter()
+        .filter_map(|c| {
+            let hotkey = c.name.chars()
+                .map(|ch| ch.to_string())
+                .find(|ch| !taken.contains(ch))?;
+            taken.push(hotkey.clone());
+            Some((hotkey, c))
+        })
+        .collect()
+}
+
+/// Lists the categories one per line, with each hotkey enclosed in brackets
+fn build_category_legend(hotkeys: &[(String, Category)], today_counts: &HashMap<String, i64>) -> String {
+    hotkeys.iter()
+        .map(|(hotkey, c)| {
+            let (i, ch) = c.name.char_indices()
+                .find(|(_, ch)| ch.to_string() == *hotkey)
+                .unwrap();
+            let entry = format!("{}[{}]{}", &c.name[..i], ch, &c.name[i + ch.len_utf8()..]);
+            match today_counts.get(&c.name) {
+                Some(count) if *count > 0 => format!("{} ({})", entry, count),
+                _ => entry,
+            }
+        })
         .collect::<Vec<_>>()
-        .join("\n");
+        .join("\n")
+}
+
+/// Builds the category column, which takes either a category's hotkey or its name, typed in full or
+/// in part; the legend shows how many events were recorded today in each category
+fn build_category_selector(categories: Rc<Vec<Category>>, default_category: Option<&str>, category_order: &[String], name_matching: NameMatching, today_counts: &HashMap<String, i64>) -> PaddedView<LinearLayout> {
+    let hotkeys = assign_category_hotkeys(&categories, category_order);
+    let categories_sign = build_category_legend(&hotkeys, today_counts);
+    let categories_keeper: HashMap<String, Category> = HashMap::from_iter(hotkeys);
+    let category_finder = Rc::new(NamedFinder::new(categories, name_matching));
+    let category_finder_for_on_edit = Rc::clone(&category_finder);
     PaddedView::lrtb(
         2, 2, 0, 0,
         LinearLayout::vertical()
@@ -326,127 +1785,746 @@ fn build_category_selector(categories: Rc<Vec<Category>>) -> PaddedView<LinearLa
         )
         .child(
             EditView::new()
-            .on_edit(|siv: &mut Cursive, query: &str, _cursor: usize| {
-                if query.len() > 1 {
+            .on_edit(move |siv: &mut Cursive, query: &str, _cursor: usize| {
+                // a comma after a category adds another one rather than replacing it
+                if query.len() > 1 && !query.ends_with(',') && category_finder_for_on_edit.exact(&query[0..query.len()-1]).is_some() {
+                    // assume that user wants to change the chosen category
                     siv.call_on_name("category_edit", |v: &mut EditView| {
                         v.set_content(query[query.len()-1..].to_string());
                     });
                 }
             })
             .on_submit(move |siv: &mut Cursive, text: &str| {
-                match categories_keeper.get(text) {
-                    Some(c) => {
-                        siv.call_on_name("category_edit", |v: &mut EditView| {
-                            v.set_content(c.name.to_string());
-                        });
-                        siv.focus_name("satisfactory_checkbox").unwrap();
-                        siv.call_on_name("recording_status", |v: &mut TextView| {
-                            v.set_content("Satisfactory?");
-                        });
-                    }
-                    None => {
-                        // this was not a valid category; try again
-                        ()
+                if !advance_allowed(siv) {
+                    return;
+                }
+                let mut chosen_names: Vec<String> = vec![];
+                for part in split_categories(text) {
+                    // a single character is a hotkey; anything longer is (part of) a name, such as
+                    // a prefilled default category
+                    let chosen = if part.chars().count() == 1 {
+                        categories_keeper.get(&part)
+                    } else {
+                        category_finder.exact(&part)
+                            .or_else(|| category_finder.find(&part).first().copied())
+                    };
+                    match chosen {
+                        Some(c) if chosen_names.contains(&c.name) => {},
+                        Some(c) => chosen_names.push(c.name.to_string()),
+                        None => {
+                            // this was not a valid category; try again
+                            let msg = format!("No category matches \"{}\"", part);
+                            siv.call_on_name("recording_status", |v: &mut TextView| {
+                                v.set_content(msg);
+                            });
+                            return;
+                        }
                     }
                 }
-            })
-            .with_name("category_edit")
-        )
-        .child(
-            TextView::new(categories_sign)
-        )
-    )
+                if chosen_names.is_empty() {
+                    siv.call_on_name("recording_status", |v: &mut TextView| {
+                        v.set_content("Choose a category");
+                    });
+                    return;
+                }
+                siv.call_on_name("category_edit", |v: &mut EditView| {
+                    v.set_content(chosen_names.join(", "));
+                });
+                siv.focus_name("satisfactory_select").unwrap();
+                let prompt = current_satisfaction_prompt(siv);
+                siv.call_on_name("recording_status", |v: &mut TextView| {
+                    v.set_content(prompt);
+                });
+            })
+            .content(default_category.unwrap_or_default())
+            .with_name("category_edit")
+        )
+        .child(
+            TextView::new(categories_sign)
+        )
+    )
+}
+
+/// Splits the text of the category field into the categories given, which are separated by commas
+/// when a contribution counts toward more than one
+fn split_categories(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.to_string())
+        .collect()
+}
+
+/// Spells out which satisfaction will be recorded, since a default left in place by mistake is
+/// easy to miss
+fn satisfaction_prompt(labels: &Labels, satisfactory: Satisfaction) -> String {
+    format!("{}: {} (type y, n, or p to change; enter to accept)", labels.satisfactory_prompt, satisfactory.to_string().to_uppercase())
+}
+
+fn current_satisfaction_prompt(siv: &mut Cursive) -> String {
+    let satisfactory = siv.find_name::<SelectView<Satisfaction>>("satisfactory_select").unwrap()
+        .selection()
+        .map_or(Satisfaction::Unsatisfactory, |s| *s);
+    satisfaction_prompt(&current_labels(siv), satisfactory)
+}
+
+fn build_satisfactory_selector(default_satisfaction: Satisfaction, header: &str) -> PaddedView<LinearLayout> {
+    let mut select = build_satisfaction_select();
+    let default_index = select.iter().position(|(_, s)| *s == default_satisfaction).unwrap_or(0);
+    select.set_selection(default_index);
+    PaddedView::lrtb(
+        2, 2, 0, 0,
+        LinearLayout::vertical()
+        .child(
+            TextView::new(header)
+        )
+        .child(
+            select
+            // typing the first letter of a state selects it
+            .autojump()
+            .on_select(|siv: &mut Cursive, satisfactory: &Satisfaction| {
+                let prompt = satisfaction_prompt(&current_labels(siv), *satisfactory);
+                siv.call_on_name("recording_status", |v: &mut TextView| {
+                    v.set_content(prompt);
+                });
+            })
+            .on_submit(|siv: &mut Cursive, _: &Satisfaction| {
+                if !advance_allowed(siv) {
+                    return;
+                }
+                siv.focus_name("recording_submit_button").unwrap();
+                // the button is disabled in a read-only session
+                let can_submit = siv.find_name::<Button>("recording_submit_button").unwrap().is_enabled();
+                siv.call_on_name("recording_status", |v: &mut TextView| {
+                    v.set_content(if can_submit { "Submit" } else { "Read-only session; nothing is recorded" });
+                });
+            })
+            .with_name("satisfactory_select")
+        )
+    )
+}
+
+fn build_satisfaction_select() -> SelectView<Satisfaction> {
+    SelectView::new()
+        .with_all([Satisfaction::Unsatisfactory, Satisfaction::Satisfactory, Satisfaction::Pending]
+            .iter()
+            .map(|s| (s.to_string(), *s)))
+}
+
+fn build_recording_buttons_column(students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>, read_only: bool) -> PaddedView<LinearLayout> {
+    let students_for_submit = Rc::clone(&students);
+    let categories_for_submit = Rc::clone(&categories);
+    let recorder_for_submit = Arc::clone(&event_recorder);
+    let students_for_main = Rc::clone(&students);
+    let categories_for_main = Rc::clone(&categories);
+    let picker_for_main = Arc::clone(&picker);
+    let recorder_for_main = Arc::clone(&event_recorder);
+    let picker_for_filter = Arc::clone(&picker);
+    let recorder_for_filter = Arc::clone(&event_recorder);
+    let students_for_skip = Rc::clone(&students);
+    let picker_for_skip = Arc::clone(&picker);
+    let students_for_retry = Rc::clone(&students);
+    let categories_for_retry = Rc::clone(&categories);
+    let picker_for_retry = Arc::clone(&picker);
+    let recorder_for_retry = Arc::clone(&event_recorder);
+    PaddedView::lrtb(
+        2, 2, 0, 0,
+        LinearLayout::vertical()
+        .child(
+            Button::new("Submit", move |siv: &mut Cursive| {
+                if !advance_allowed(siv) {
+                    return;
+                }
+                submit_recording_form(siv, &students_for_submit, &categories_for_submit, &picker, &recorder_for_submit);
+            })
+            .with_enabled(!read_only)
+            .with_name("recording_submit_button")
+        )
+        .child(
+            // enabled once a submission fails with a database error, such as a dropped connection
+            Button::new("Retry", move |siv: &mut Cursive| {
+                retry_recording(siv, &students_for_retry, &categories_for_retry, &picker_for_retry, &recorder_for_retry);
+            })
+            .disabled()
+            .with_name("recording_retry_button")
+        )
+        .child(
+            // toggles recording against a past date and time
+            Button::new("Advanced", |siv: &mut Cursive| {
+                let advanced = siv.find_name::<RecordingWhenRow>("recording_when_row").unwrap().is_visible();
+                if advanced {
+                    siv.call_on_name("recording_when_row", |v: &mut RecordingWhenRow| {
+                        v.hide();
+                    });
+                    siv.call_on_name("recording_status", |v: &mut TextView| {
+                        v.set_content("Recording as of now");
+                    });
+                } else {
+                    show_recording_when(siv, Local::now());
+                    siv.focus_name("recording_when_edit").unwrap();
+                    siv.call_on_name("recording_status", |v: &mut TextView| {
+                        v.set_content("Enter when the event happened");
+                    });
+                }
+            })
+        )
+        .child(
+            // toggles recording against yesterday's class, for back-filling the morning after
+            Button::new("Yesterday", |siv: &mut Cursive| {
+                toggle_recording_yesterday(siv);
+            })
+        )
+        .child(
+            // gives the randomly chosen student's turn to someone else for now
+            Button::new("Skip", move |siv: &mut Cursive| {
+                skip_student(siv, &students_for_skip, &picker_for_skip);
+            })
+        )
+        .child(
+            Button::new("Filter", move |siv: &mut Cursive| {
+                siv.add_layer(build_picker_filter_dialog(
+                    Arc::clone(&picker_for_filter),
+                    Arc::clone(&recorder_for_filter)
+                ));
+            })
+        )
+        .child(
+            Button::new("Back to main", move |siv: &mut Cursive| {
+                siv.pop_layer();
+                siv.add_layer(build_main_menu(
+                    Rc::clone(&students_for_main),
+                    Rc::clone(&categories_for_main),
+                    Arc::clone(&picker_for_main),
+                    Arc::clone(&recorder_for_main)))
+            })
+            .with_name("recording_back_button")
+        )
+    )
+}
+
+/// Builds the column listing the students recorded this session; it is filled in by
+/// `show_session_tally` once the recording dialog is showing
+fn build_session_tally_column() -> PaddedView<ResizedView<LinearLayout>> {
+    PaddedView::lrtb(
+        1, 1, 0, 0,
+        LinearLayout::vertical()
+        .child(TextView::new("This session"))
+        .child(
+            TextView::new("")
+            .with_name("session_tally")
+            .scrollable()
+        )
+        .fixed_width(24)
+    )
+}
+
+/// Fills in what the recording dialog shows of this session: the students recorded so far and
+/// whether a failed submission can be retried
+fn show_recording_session(siv: &mut Cursive) {
+    show_session_tally(siv);
+    let can_retry = siv.user_data::<MenuState>().is_some_and(|state| state.failed_recording.is_some());
+    siv.call_on_name("recording_retry_button", |v: &mut Button| {
+        v.set_enabled(can_retry);
+    });
+}
+
+fn show_session_tally(siv: &mut Cursive) {
+    let tally = current_session_tally(siv);
+    siv.call_on_name("session_tally", |v: &mut TextView| {
+        v.set_content(format_session_tally(&tally));
+    });
+}
+
+/// Lists the students recorded this session, most often recorded first, so that those who have
+/// not had a turn yet stand out by their absence
+fn format_session_tally(tally: &HashMap<String, u32>) -> String {
+    if tally.is_empty() {
+        return "No one yet".to_string();
+    }
+    let mut entries: Vec<(&String, &u32)> = tally.iter().collect();
+    entries.sort_by(|(a_name, a_count), (b_name, b_count)| b_count.cmp(a_count).then_with(|| a_name.cmp(b_name)));
+    let mut lines = vec![format!("Students recorded: {}", tally.len())];
+    lines.extend(entries.iter().map(|(name, count)| format!("{} ({})", name, count)));
+    lines.join("\n")
+}
+
+/// Puts the student in the recording dialog back into the picker's current pass and chooses the
+/// next random student in their place
+fn skip_student(siv: &mut Cursive, students: &Rc<Vec<Student>>, picker: &Arc<Mutex<StudentPicker>>) {
+    let query = siv.find_name::<EditView>("recording_student_query").unwrap().get_content();
+    let requeued = match students.iter().find(|s| s.name == *query) {
+        Some(student) => picker.lock().unwrap().requeue(student.db_id),
+        None => false,
+    };
+    if requeued {
+        choose_random_student(siv, picker);
+    } else {
+        siv.call_on_name("recording_status", |v: &mut TextView| {
+            v.set_content("Only randomly chosen students can be skipped");
+        });
+    }
+}
+
+/// Fills the recording dialog with the top matching student, the default category, and a
+/// satisfactory contribution, then submits it
+fn quick_record(siv: &mut Cursive, students: &Rc<Vec<Student>>, categories: &Rc<Vec<Category>>, picker: &Arc<Mutex<StudentPicker>>, event_recorder: &Arc<Mutex<EventRecorder>>) {
+    if event_recorder.lock().unwrap().read_only() {
+        siv.call_on_name("recording_status", |v: &mut TextView| {
+            v.set_content("Read-only session; nothing is recorded");
+        });
+        return;
+    }
+    let default_category = match event_recorder.lock().unwrap().default_category() {
+        Some(c) => c.to_string(),
+        None => {
+            siv.call_on_name("recording_status", |v: &mut TextView| {
+                v.set_content("No default category; start with --default-category to use Ctrl-Y");
+            });
+            return;
+        }
+    };
+    let query = siv.find_name::<EditView>("recording_student_query").unwrap().get_content();
+    let choice = if students.iter().any(|s| s.name == *query) {
+        Some(query.to_string())
+    } else {
+        siv.call_on_name("recording_student_matches", |v: &mut SelectView| {
+            v.get_item(0).map(|(_, name)| name.to_string())
+        }).unwrap()
+    };
+    let choice = match choice {
+        Some(c) if !query.is_empty() => c,
+        _ => {
+            siv.call_on_name("recording_status", |v: &mut TextView| {
+                v.set_content("No matching student; try again");
+            });
+            return;
+        }
+    };
+    siv.call_on_name("recording_student_query", |v: &mut EditView| {
+        v.set_content(choice);
+    });
+    siv.call_on_name("category_edit", |v: &mut EditView| {
+        v.set_content(default_category);
+    });
+    siv.call_on_name("satisfactory_select", |v: &mut SelectView<Satisfaction>| {
+        let satisfactory_index = v.iter().position(|(_, s)| *s == Satisfaction::Satisfactory);
+        if let Some(i) = satisfactory_index {
+            v.set_selection(i);
+        }
+    });
+    submit_recording_form(siv, students, categories, picker, event_recorder);
+}
+
+/// Records the event filled into the recording dialog, asking first if it looks like a duplicate
+fn submit_recording_form(siv: &mut Cursive, students: &Rc<Vec<Student>>, categories: &Rc<Vec<Category>>, picker: &Arc<Mutex<StudentPicker>>, event_recorder: &Arc<Mutex<EventRecorder>>) {
+    siv.call_on_name("recording_status", |v: &mut TextView| {
+        v.set_content("Submit button pushed");
+    });
+    let student_name: Rc<String> = siv.find_name::<EditView>("recording_student_query").unwrap().get_content();
+    let category_name: Rc<String> = siv.find_name::<EditView>("category_edit").unwrap().get_content();
+    let satisfactory: Satisfaction = siv.find_name::<SelectView<Satisfaction>>("satisfactory_select").unwrap()
+        .selection()
+        .map_or(Satisfaction::Unsatisfactory, |s| *s);
+    let when = match read_recording_when(siv) {
+        Ok(w) => w,
+        Err(msg) => {
+            siv.call_on_name("recording_status", |v: &mut TextView| {
+                v.set_content(msg);
+            });
+            return;
+        }
+    };
+    let attempt = RecordingAttempt {
+        student_name: student_name.to_string(),
+        category_names: split_categories(&category_name),
+        satisfactory: satisfactory,
+        when: when,
+    };
+    record_unless_duplicate(siv, students, categories, picker, event_recorder, attempt);
+}
+
+/// Records the attempt, asking first if it looks like a duplicate of an event just recorded
+fn record_unless_duplicate(siv: &mut Cursive, students: &Rc<Vec<Student>>, categories: &Rc<Vec<Category>>, picker: &Arc<Mutex<StudentPicker>>, event_recorder: &Arc<Mutex<EventRecorder>>, attempt: RecordingAttempt) {
+    let duplicate = {
+        let mut recorder = event_recorder.lock().unwrap();
+        attempt.category_names.iter()
+            .map(|c| recorder.is_recent_duplicate(&attempt.student_name, c, attempt.satisfactory, attempt.when))
+            .collect::<Result<Vec<bool>, _>>()
+            .map(|found| found.contains(&true))
+    };
+    match duplicate {
+        Ok(true) => {
+            let students_for_confirm = Rc::clone(students);
+            let categories_for_confirm = Rc::clone(categories);
+            let picker_for_confirm = Arc::clone(picker);
+            let recorder_for_confirm = Arc::clone(event_recorder);
+            siv.add_layer(
+                Dialog::around(TextView::new(format!(
+                    "{} was just recorded for {} as {}.\nLooks like a duplicate; record anyway?",
+                    attempt.category_names.join(", "), attempt.student_name, attempt.satisfactory
+                )))
+                .button("Record anyway", move |confirm_siv: &mut Cursive| {
+                    confirm_siv.pop_layer();
+                    record_attempt(confirm_siv, &students_for_confirm, &categories_for_confirm, &picker_for_confirm, &recorder_for_confirm, &attempt);
+                })
+                .dismiss_button("Cancel")
+            );
+        },
+        Ok(false) => {
+            record_attempt(siv, students, categories, picker, event_recorder, &attempt);
+        },
+        Err(e) => {
+            siv.call_on_name("recording_status", |v: &mut TextView| {
+                v.set_content(format!("Error while checking for duplicates: {}", e));
+            });
+        }
+    }
+}
+
+/// What was entered in the recording dialog for one submission, kept after a database error so
+/// that the same event can be retried without entering it again
+#[derive(Clone, Debug)]
+struct RecordingAttempt {
+    student_name: String,
+    category_names: Vec<String>,
+    satisfactory: Satisfaction,
+    // when the event happened, if it is being back-filled
+    when: Option<DateTime<Local>>,
+}
+
+/// Records the attempt and, once it is recorded, rebuilds the recording dialog for the next event
+fn record_attempt(siv: &mut Cursive, students: &Rc<Vec<Student>>, categories: &Rc<Vec<Category>>, picker: &Arc<Mutex<StudentPicker>>, event_recorder: &Arc<Mutex<EventRecorder>>, attempt: &RecordingAttempt) {
+    if let Some(recorded_at) = record_event(siv, event_recorder, attempt) {
+        siv.pop_layer();
+        let name_matching = current_name_matching(siv);
+        let labels = current_labels(siv);
+        siv.add_layer(build_recording_dialog(
+            Rc::clone(students),
+            Rc::clone(categories),
+            Arc::clone(picker),
+            Arc::clone(event_recorder),
+            name_matching,
+            &labels,
+            &submitted_msg(&attempt.student_name, &attempt.category_names, attempt.satisfactory, &labels, recorded_at, attempt.when.is_some())
+        ));
+        show_recording_session(siv);
+        // stay in advanced mode while back-filling
+        if let Some(w) = attempt.when {
+            show_recording_when(siv, w);
+        }
+        append_to_transcript(siv, event_recorder, &attempt.student_name, &attempt.category_names, attempt.satisfactory, recorded_at);
+    }
+}
+
+/// Records the last submission that failed with a database error again, as it was entered,
+/// asking first if it looks like it was recorded after all
+fn retry_recording(siv: &mut Cursive, students: &Rc<Vec<Student>>, categories: &Rc<Vec<Category>>, picker: &Arc<Mutex<StudentPicker>>, event_recorder: &Arc<Mutex<EventRecorder>>) {
+    let attempt = siv.user_data::<MenuState>().and_then(|state| state.failed_recording.clone());
+    match attempt {
+        Some(attempt) => record_unless_duplicate(siv, students, categories, picker, event_recorder, attempt),
+        None => {
+            siv.call_on_name("recording_status", |v: &mut TextView| {
+                v.set_content("Nothing to retry");
+            });
+        }
+    }
+}
+
+/// Adds just-recorded events, one per category, to the session transcript, noting in the
+/// recording status if that failed; the events themselves stay recorded either way
+fn append_to_transcript(siv: &mut Cursive, event_recorder: &Arc<Mutex<EventRecorder>>, student_name: &str, category_names: &[String], satisfactory: Satisfaction, when: DateTime<Local>) {
+    let recorder = event_recorder.lock().unwrap();
+    let appended = category_names.iter()
+        .try_for_each(|c| recorder.append_transcript(student_name, c, satisfactory, when));
+    drop(recorder);
+    if let Err(e) = appended {
+        siv.call_on_name("recording_status", |v: &mut TextView| {
+            v.set_content(format!("Submitted, but could not add to the transcript: {}", e));
+        });
+    }
+}
+
+/// Records an event in each of the attempt's categories as of its time, or now if not given,
+/// reporting any problem in the recording status; returns when the events were stored as having
+/// happened if they were recorded
+///
+/// After a database error, the attempt is kept and the Retry button enabled.
+fn record_event(siv: &mut Cursive, event_recorder: &Arc<Mutex<EventRecorder>>, attempt: &RecordingAttempt) -> Option<DateTime<Local>> {
+    let student_name = attempt.student_name.as_str();
+    let category_names = attempt.category_names.as_slice();
+    let satisfactory = attempt.satisfactory;
+    let when = attempt.when;
+    // a name that matches nothing would only fail as a database error about a missing id
+    let unknown = event_recorder.lock().unwrap().find_unknown_names(student_name, category_names);
+    if let Ok(unknown) = unknown {
+        if !unknown.is_empty() {
+            let problems: Vec<String> = unknown.iter()
+                .map(|name| match name {
+                    UnknownName::Student(name) => format!("no student is named {}", name),
+                    UnknownName::Category(name) => format!("no category is named {}", name),
+                })
+                .collect();
+            siv.call_on_name("recording_status", |v: &mut TextView| {
+                v.set_content(format!("Not submitted: {}", problems.join("; ")));
+            });
+            return None;
+        }
+    }
+    let recorded = match (category_names, when) {
+        ([category_name], Some(w)) => event_recorder.lock().unwrap().record_at(student_name, category_name, satisfactory, w),
+        ([category_name], None) => event_recorder.lock().unwrap().record(student_name, category_name, satisfactory),
+        _ => event_recorder.lock().unwrap().record_many(student_name, category_names, satisfactory, when),
+    };
+    match recorded {
+        Ok(n) => {
+            match n {
+                n if n == category_names.len() as u64 && n > 0 => {
+                    siv.with_user_data(|state: &mut MenuState| {
+                        *state.session_tally.entry(student_name.to_string()).or_insert(0) += 1;
+                        state.failed_recording = None;
+                    });
+                    Some(event_recorder.lock().unwrap().last_recorded_at().unwrap_or_else(Local::now))
+                },
+                _ => {
+                    siv.call_on_name("recording_status", |v: &mut TextView| {
+                        v.set_content(format!("Problem: submitted {} (are all fields correct?)", n));
+                    });
+                    None
+                }
+            }
+        },
+        Err(e) => {
+            match e.as_db_error() {
+                Some(dbe) => {
+                    siv.call_on_name("recording_status", |v: &mut TextView| {
+                        v.set_content(format!("DB Error ({}): {}; Retry records it again", dbe.severity(), dbe.message()));
+                    });
+                },
+                // the server may have stored the event just before the connection dropped
+                None if e.is_closed() => {
+                    siv.call_on_name("recording_status", |v: &mut TextView| {
+                        v.set_content("Connection lost while submitting, so it may already be recorded; check before using Retry");
+                    });
+                },
+                None => {
+                    siv.call_on_name("recording_status", |v: &mut TextView| {
+                        v.set_content(format!("Error: {}; Retry records it again", e));
+                    });
+                }
+            }
+            siv.with_user_data(|state: &mut MenuState| {
+                state.failed_recording = Some(attempt.clone());
+            });
+            siv.call_on_name("recording_retry_button", |v: &mut Button| {
+                v.enable();
+            });
+            None
+        }
+    }
 }
 
-fn build_satisfactory_selector() -> PaddedView<LinearLayout> {
-    PaddedView::lrtb(
-        2, 2, 0, 0,
+/// Builds a dialog restricting random choices to students within a range of points
+fn build_picker_filter_dialog(picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>) -> Dialog {
+    let picker_for_clear = Arc::clone(&picker);
+    Dialog::around(
         LinearLayout::vertical()
         .child(
-            TextView::new("?")
+            TextView::new("Choose randomly among students with points in this range (blank for no limit):")
         )
         .child(
-            Checkbox::new()
-            .on_change(|siv: &mut Cursive, _val: bool| {
-                siv.focus_name("recording_submit_button").unwrap();
-                siv.call_on_name("recording_status", |v: &mut TextView| {
-                    v.set_content("Submit");
-                });
-            })
-            .with_name("satisfactory_checkbox")
-        )
-    )
-}
-
-fn build_recording_buttons_column(students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>) -> PaddedView<LinearLayout> {
-    let students_for_submit = Rc::clone(&students);
-    let categories_for_submit = Rc::clone(&categories);
-    let recorder_for_submit = Arc::clone(&event_recorder);
-    let students_for_main = Rc::clone(&students);
-    let categories_for_main = Rc::clone(&categories);
-    let picker_for_main = Arc::clone(&picker);
-    let recorder_for_main = Arc::clone(&event_recorder);
-    PaddedView::lrtb(
-        2, 2, 0, 0,
-        LinearLayout::vertical()
-        .child(
-            Button::new("Submit", move |siv: &mut Cursive| {
-                siv.call_on_name("recording_status", |v: &mut TextView| {
-                    v.set_content("Submit button pushed");
-                });
-                let student_name: Rc<String> = siv.find_name::<EditView>("recording_student_query").unwrap().get_content();
-                let category_name: Rc<String> = siv.find_name::<EditView>("category_edit").unwrap().get_content();
-                let satisfactory: bool = siv.find_name::<Checkbox>("satisfactory_checkbox").unwrap().is_checked();
-                match recorder_for_submit.lock().unwrap().record(&student_name, &category_name, satisfactory) {
-                    Ok(n) => {
-                        match n {
-                            1 => {
-                                siv.pop_layer();
-                                siv.add_layer(build_recording_dialog(
-                                    Rc::clone(&students_for_submit),
-                                    Rc::clone(&categories_for_submit),
-                                    Arc::clone(&picker),
-                                    Arc::clone(&recorder_for_submit),
-                                    "Submitted successfully"
-                                ))
-                            },
-                            _ => {
-                                siv.call_on_name("recording_status", |v: &mut TextView| {
-                                    v.set_content(format!("Problem: submitted {} (are all fields correct?)", n));
-                                });
-                            }
-                        }
-                    },
-                    Err(e) => {
-                        match e.as_db_error() {
-                            Some(dbe) => {
-                                siv.call_on_name("recording_status", |v: &mut TextView| {
-                                    v.set_content(format!("DB Error ({}): {}", dbe.severity(), dbe.message()));
-                                });
-                            },
-                            None => {
-                                siv.call_on_name("recording_status", |v: &mut TextView| {
-                                    v.set_content(format!("Error: {}", e));
-                                });
-                            }
-                        }
-                    }
-                };
-            })
-            .with_name("recording_submit_button")
+            LinearLayout::horizontal()
+            .child(TextView::new("At least "))
+            .child(EditView::new().with_name("filter_min_edit").fixed_width(6))
+            .child(TextView::new(" at most "))
+            .child(EditView::new().with_name("filter_max_edit").fixed_width(6))
         )
         .child(
-            Button::new("Back to main", move |siv: &mut Cursive| {
-                siv.pop_layer();
-                siv.add_layer(build_main_menu(
-                    Rc::clone(&students_for_main),
-                    Rc::clone(&categories_for_main),
-                    Arc::clone(&picker_for_main),
-                    Arc::clone(&recorder_for_main)))
-            })
-            .with_name("recording_back_button")
+            TextView::new("Ready")
+            .with_name("filter_status_msg")
         )
     )
+    .title("Picker Filter")
+    .button("Apply", move |siv: &mut Cursive| {
+        let mut bounds = vec![];
+        for name in &["filter_min_edit", "filter_max_edit"] {
+            let text = siv.find_name::<EditView>(name).unwrap().get_content();
+            let text = text.trim();
+            if text.is_empty() {
+                bounds.push(None);
+            } else {
+                match text.parse::<i32>() {
+                    Ok(n) => bounds.push(Some(n)),
+                    Err(_) => {
+                        siv.call_on_name("filter_status_msg", |v: &mut TextView| {
+                            v.set_content(format!("Not a number: {}", text));
+                        });
+                        return;
+                    }
+                }
+            }
+        }
+        let (min, max) = (bounds[0], bounds[1]);
+        // bring the summary up to date so that eligibility reflects today's events
+        let points = {
+            let mut recorder = event_recorder.lock().unwrap();
+            recorder.refresh_summary().and_then(|()| recorder.get_points())
+        };
+        let points = match points {
+            Ok(p) => p,
+            Err(e) => {
+                siv.call_on_name("filter_status_msg", |v: &mut TextView| {
+                    v.set_content(format!("Database error: {}", e));
+                });
+                return;
+            }
+        };
+        let eligible: HashSet<i32> = points.iter()
+            .filter(|(_, p)| min.is_none_or(|m| **p >= m) && max.is_none_or(|m| **p <= m))
+            .map(|(id, _)| *id)
+            .collect();
+        let count = picker.lock().unwrap().set_eligible(Some(&eligible));
+        if count == 0 {
+            siv.call_on_name("filter_status_msg", |v: &mut TextView| {
+                v.set_content("No students have points in this range");
+            });
+            return;
+        }
+        siv.pop_layer();
+        siv.call_on_name("recording_status", |v: &mut TextView| {
+            v.set_content(format!("Choosing among {} students", count));
+        });
+    })
+    .button("Clear", move |siv: &mut Cursive| {
+        let count = picker_for_clear.lock().unwrap().set_eligible(None);
+        siv.pop_layer();
+        siv.call_on_name("recording_status", |v: &mut TextView| {
+            v.set_content(format!("Choosing among all {} students", count));
+        });
+    })
+    .dismiss_button("Cancel")
+}
+
+/// Where the last successful export of a schema was written is remembered here, so that courses
+/// exporting to different files do not overwrite each other's choice
+fn export_path_file(schema: &str) -> PathBuf {
+    PathBuf::from(format!("data/export_path_{}.txt", schema))
+}
+
+/// The path that the export dialog starts out with: the last one exported to for this schema, or
+/// the usual file if there was none
+fn last_export_path(schema: &str) -> String {
+    fs::read_to_string(export_path_file(schema))
+        .ok()
+        .map(|contents| contents.trim_end_matches('\n').to_string())
+        .filter(|path| !path.is_empty())
+        .unwrap_or_else(|| "data/participation_points.tsv".to_string())
+}
+
+fn remember_export_path(schema: &str, path: &str) -> std::io::Result<()> {
+    fs::write(export_path_file(schema), format!("{}\n", path))
+}
+
+/// The choices of what to export in the export dialog, whose details are read from the rest of
+/// the dialog when submitting
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ExportKind {
+    Periods,
+    PeriodsWithDropped,
+    Detailed,
+    DateRange,
+    AtRisk,
+}
+
+/// What the export dialog writes
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ExportContents {
+    // points per period, for UBLearns
+    Periods,
+    // points per period, also listing students who dropped
+    PeriodsWithDropped,
+    // points per period and category, for review
+    Detailed,
+    // points from the start of the first date through the end of the second
+    DateRange(Date<Local>, Date<Local>),
+    // enrolled students with fewer than this many points
+    AtRisk(i32),
+}
+
+const EXPORT_DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Reads the first and last dates of a date range export from the export dialog
+fn read_export_dates(siv: &mut Cursive) -> Result<(Date<Local>, Date<Local>), String> {
+    let mut dates = vec![];
+    for name in &["exporting_from_edit", "exporting_to_edit"] {
+        let text = siv.find_name::<EditView>(name).unwrap().get_content();
+        let d = NaiveDate::parse_from_str(&text, EXPORT_DATE_FORMAT)
+            .map_err(|_| format!("Dates must look like {}", Local::today().format(EXPORT_DATE_FORMAT)))?;
+        dates.push(Local.ymd(d.year(), d.month(), d.day()));
+    }
+    if dates[0] > dates[1] {
+        return Err("The first date must not be after the last".to_string());
+    }
+    Ok((dates[0], dates[1]))
+}
+
+/// Writes the chosen contents to the chosen file, replacing the export dialog with a progress
+/// message; points per period are limited to the students of `section` if one is given
+fn export_to(siv: &mut Cursive, chosen: &str, event_recorder: &Arc<Mutex<EventRecorder>>, contents: ExportContents, encoding: ExportEncoding, section: Option<&str>) {
+    match File::create(chosen) {
+        Ok(mut outfile) => {
+            siv.pop_layer();
+            siv.add_layer(Dialog::around(TextView::new("Starting export").with_name("export_msg")).dismiss_button("Ok"));
+            // only exports for review are stamped with the term; uploads must keep their format
+            let term_label = match event_recorder.lock().unwrap().term_label() {
+                Ok(term_label) => term_label,
+                Err(e) => {
+                    display_export_msg(siv, &format!("Database error: {}", e));
+                    return;
+                }
+            };
+            let exported = match contents {
+                ExportContents::Periods | ExportContents::PeriodsWithDropped => {
+                    let include_dropped = contents == ExportContents::PeriodsWithDropped;
+                    let rows = event_recorder.lock().unwrap().get_summary(include_dropped, section);
+                    rows.map(|r| export_summary(r, ExportIdentifier::Username, encoding, &mut outfile))
+                },
+                ExportContents::Detailed => {
+                    let summary = event_recorder.lock().unwrap().get_detailed_summary();
+                    summary.map(|s| export_detailed_summary(s, term_label.as_deref(), ExportIdentifier::Username, encoding, &mut outfile))
+                },
+                ExportContents::DateRange(first, last) => {
+                    let rows = event_recorder.lock().unwrap().get_range_summary(&first, &last);
+                    let label = format!("{} to {}", first.format(EXPORT_DATE_FORMAT), last.format(EXPORT_DATE_FORMAT));
+                    rows.map(|r| export_range_summary(r, &label, term_label.as_deref(), ExportIdentifier::Username, encoding, &mut outfile))
+                },
+                ExportContents::AtRisk(min_points) => {
+                    let students = event_recorder.lock().unwrap().list_at_risk(min_points);
+                    let label = format!("points, below the minimum of {}", min_points);
+                    students.map(|s| {
+                        let rows = s.iter().map(|student| student.to_summary_row()).collect();
+                        export_range_summary(rows, &label, term_label.as_deref(), ExportIdentifier::Username, encoding, &mut outfile)
+                    })
+                },
+            };
+            match exported {
+                Ok(Ok(())) => {
+                    let schema = event_recorder.lock().unwrap().schema().to_string();
+                    // failing to remember the path only means retyping it next time
+                    let _ = remember_export_path(&schema, chosen);
+                    display_export_msg(siv, &*format!("Finished export:\n{}", chosen));
+                },
+                Ok(Err(e)) => { display_export_msg(siv, &*format!("File error: {}", e)); },
+                Err(e) => {
+                    display_export_msg(siv, &*format!("Database error: {}", e));
+                }
+            }
+        },
+        Err(e) => {
+            siv.call_on_name("exporting_status_msg", |v: &mut TextView| {
+                v.set_content(format!("File opening error: {:?}", e))
+            });
+        }
+    }
 }
 
 fn display_export_msg(siv: &mut Cursive, msg: &str) {
@@ -456,17 +2534,21 @@ fn display_export_msg(siv: &mut Cursive, msg: &str) {
     };
 }
 
-fn build_redeeming_dialog_input(students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>) -> Dialog {
+fn build_redeeming_dialog_input(students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>, name_matching: NameMatching) -> Dialog {
+    let category_order = event_recorder.lock().unwrap().category_order().to_vec();
     Dialog::around(
         LinearLayout::vertical()
         .child(
             LinearLayout::horizontal()
             .child(
-                build_redeeming_student_selector(Rc::clone(&students))
+                build_redeeming_student_selector(Rc::clone(&students), name_matching)
             )
             .child(
                 build_date_selector()
             )
+            .child(
+                build_redeeming_category_selector(Rc::clone(&categories), &category_order)
+            )
             .child(
                 build_redeeming_buttons_column(
                     Rc::clone(&students),
@@ -483,11 +2565,10 @@ fn build_redeeming_dialog_input(students: Rc<Vec<Student>>, categories: Rc<Vec<C
     )
 }
 
-fn build_redeeming_student_selector(students: Rc<Vec<Student>>) -> PaddedView<ResizedView<LinearLayout>> {
-    let student_finder = Rc::new(NamedFinder::new(Rc::clone(&students)));
-    let students_for_on_edit = Rc::clone(&students);
+fn build_redeeming_student_selector(students: Rc<Vec<Student>>, name_matching: NameMatching) -> PaddedView<ResizedView<LinearLayout>> {
+    let student_finder = Rc::new(NamedFinder::new(Rc::clone(&students), name_matching));
     let student_finder_for_on_edit = Rc::clone(&student_finder);
-    let students_for_on_submit = Rc::clone(&students);
+    let student_finder_for_on_submit = Rc::clone(&student_finder);
     PaddedView::lrtb(
         2, 2, 0, 0,
         LinearLayout::vertical()
@@ -498,26 +2579,18 @@ fn build_redeeming_student_selector(students: Rc<Vec<Student>>) -> PaddedView<Re
             EditView::new()
             // update results every time the query changes
             .on_edit(move |siv: &mut Cursive, query: &str, _cursor: usize| {
-                if query.len() > 1 && students_for_on_edit.iter().find(|s| s.name == query[0..query.len()-1]).is_some() {
+                if query.len() > 1 && student_finder_for_on_edit.exact(&query[0..query.len()-1]).is_some() {
                     // assume that user wants to change selection
                     let query = &query[query.len()-1..];
                     siv.call_on_name("redeeming_student_query", |v: &mut EditView| {
                         v.set_content(query.to_string());
                     });
-                    let matches = student_finder_for_on_edit.find(query);
-                    // Update the `matches` view with the filtered array of student names
-                    siv.call_on_name("redeeming_student_matches", |v: &mut SelectView| {
-                        v.clear();
-                        v.add_all_str(matches.iter().map(|s| s.name.to_string()));
-                    });
+                    let matches = student_finder_for_on_edit.find_with_indices(query);
+                    show_redeeming_student_matches(siv, &matches);
                 } else {
                     // update without changing query
-                    let matches = student_finder_for_on_edit.find(query);
-                    // Update the `matches` view with the filtered array of student names
-                    siv.call_on_name("redeeming_student_matches", |v: &mut SelectView| {
-                        v.clear();
-                        v.add_all_str(matches.iter().map(|s| s.name.to_string()));
-                    });
+                    let matches = student_finder_for_on_edit.find_with_indices(query);
+                    show_redeeming_student_matches(siv, &matches);
                 }
                 siv.call_on_name("redeeming_status", |v: &mut TextView| {
                     v.set_content("Select student");
@@ -525,7 +2598,7 @@ fn build_redeeming_student_selector(students: Rc<Vec<Student>>) -> PaddedView<Re
             })
             // if possible, select student when pressing enter on this edit view
             .on_submit(move |siv: &mut Cursive, text: &str| {
-                if text.len() > 0 && students_for_on_submit.iter().find(|s| s.name == text).is_none() {
+                if text.len() > 0 && student_finder_for_on_submit.exact(text).is_none() {
                     // try to get the top matching student
                     let choice = siv.call_on_name("redeeming_student_matches", |v: &mut SelectView| {
                         match v.get_item(0) {
@@ -567,6 +2640,17 @@ fn build_redeeming_student_selector(students: Rc<Vec<Student>>) -> PaddedView<Re
     )
 }
 
+/// Updates the redeeming input's matches with the students found for the typed text
+fn show_redeeming_student_matches(siv: &mut Cursive, matches: &[(&Student, Vec<usize>)]) {
+    let highlight = highlighting_matches(siv);
+    siv.call_on_name("redeeming_student_matches", |v: &mut SelectView| {
+        v.clear();
+        v.add_all(matches.iter().map(|(s, matched)| {
+            (emphasize_chars(&s.name, if highlight { matched } else { &[] }), s.name.to_string())
+        }));
+    });
+}
+
 fn build_date_selector() -> PaddedView<ResizedView<LinearLayout>> {
     PaddedView::lrtb(
         2, 2, 0, 0,
@@ -578,7 +2662,7 @@ fn build_date_selector() -> PaddedView<ResizedView<LinearLayout>> {
             EditView::new()
             .content(format!("{}", Local::today().format("%Y-%m-%d")))
             .on_submit(move |siv: &mut Cursive, _text: &str| {
-                siv.focus_name("redeeming_retrieve_button").unwrap();
+                siv.focus_name("redeeming_category_select").unwrap();
             })
             .with_name("redeeming_date_edit")
         )
@@ -586,7 +2670,40 @@ fn build_date_selector() -> PaddedView<ResizedView<LinearLayout>> {
     )
 }
 
+fn build_redeeming_category_selector(categories: Rc<Vec<Category>>, category_order: &[String]) -> PaddedView<ResizedView<LinearLayout>> {
+    let sorted_categories = Vec::from_iter(order_categories(&categories, category_order).into_iter()
+        .map(|c| c.name)
+    );
+    PaddedView::lrtb(
+        2, 2, 0, 0,
+        LinearLayout::vertical()
+        .child(
+            TextView::new("Category")
+        )
+        .child(
+            SelectView::<Option<String>>::new()
+            .popup()
+            // no filter by default
+            .item("(all)", None)
+            .with_all(sorted_categories.into_iter().map(|c| (c.to_string(), Some(c))))
+            .on_submit(|siv: &mut Cursive, _: &Option<String>| {
+                siv.focus_name("redeeming_retrieve_button").unwrap();
+            })
+            .with_name("redeeming_category_select")
+        )
+        .child(
+            TextView::new(build_category_legend(&assign_category_hotkeys(&categories, category_order), &HashMap::new()))
+        )
+        .fixed_width(20)
+    )
+}
+
 fn build_redeeming_buttons_column(students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>) -> PaddedView<LinearLayout> {
+    let recorder_for_fewest = Arc::clone(&event_recorder);
+    let recorder_for_most = Arc::clone(&event_recorder);
+    // how many students each button has already stepped past
+    let fewest_skipped = Rc::new(Cell::new(0));
+    let most_skipped = Rc::new(Cell::new(0));
     let students_for_main = Rc::clone(&students);
     let categories_for_main = Rc::clone(&categories);
     let picker_for_main = Arc::clone(&picker);
@@ -598,10 +2715,16 @@ fn build_redeeming_buttons_column(students: Rc<Vec<Student>>, categories: Rc<Vec
             Button::new("Retrieve", move |siv: &mut Cursive| {
                 let student_name: Rc<String> = siv.find_name::<EditView>("redeeming_student_query").unwrap().get_content();
                 let date_str: Rc<String> = siv.find_name::<EditView>("redeeming_date_edit").unwrap().get_content();
+                let category_name: Option<String> = siv.find_name::<SelectView<Option<String>>>("redeeming_category_select").unwrap()
+                    .selection()
+                    .and_then(|c| (*c).clone());
                 match NaiveDate::parse_from_str(&date_str, "%Y-%m-%d") {
                     Ok(d) => {
                         let d = Local.ymd(d.year(), d.month(), d.day());
-                        match event_recorder.lock().unwrap().retrieve_events(&student_name, &d) {
+                        // the recorder is locked again while building the chooser, so it must not
+                        // stay locked for the whole match
+                        let retrieved = event_recorder.lock().unwrap().retrieve_events(&student_name, &d, category_name.as_deref());
+                        match retrieved {
                             Ok(rows) => {
                                 siv.pop_layer();
                                 siv.add_layer(build_redeeming_dialog_choose(
@@ -639,6 +2762,16 @@ fn build_redeeming_buttons_column(students: Rc<Vec<Student>>, categories: Rc<Vec
             })
             .with_name("redeeming_retrieve_button")
         )
+        .child(
+            Button::new("Fewest points", move |siv: &mut Cursive| {
+                fill_redeeming_student_by_points(siv, &recorder_for_fewest, false, &fewest_skipped);
+            })
+        )
+        .child(
+            Button::new("Most points", move |siv: &mut Cursive| {
+                fill_redeeming_student_by_points(siv, &recorder_for_most, true, &most_skipped);
+            })
+        )
         .child(
             Button::new("Back to main", move |siv: &mut Cursive| {
                 siv.pop_layer();
@@ -653,87 +2786,245 @@ fn build_redeeming_buttons_column(students: Rc<Vec<Student>>, categories: Rc<Vec
     )
 }
 
-fn build_redeeming_dialog_choose(student_name: &str, chosen_date: Date<Local>, rows: Vec<postgres::Row>, students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>) -> Dialog {
-    let mut data = LinearLayout::vertical();
-    let id_width: usize = 4;
-    let category_width: usize = 10;
-    let date_width: usize = 20;
-    let satisfactory_width: usize = 4;
-    let rows_len = rows.len();
-    for (i, row) in rows.iter().enumerate() {
-        let event_id: i32 = row.get(0);
-        let category_name: String = row.get(1);
-        let first_entered: DateTime<Local> = row.get(2);
-        let sat: bool = row.get(3);
-        data.add_child(LinearLayout::horizontal()
-            .child(
-                TextView::new(format!("{}", event_id))
-                .h_align(HAlign::Right)
-                .fixed_width(id_width)
-            )
-            .child(DummyView)
-            .child(
-                TextView::new(format!("{}", category_name))
-                .fixed_width(category_width)
-            )
-            .child(DummyView)
+/// Fills the redeeming student field with the student with the fewest points, or the most if
+/// `most`, moving on to the next student each time it is called and starting over after the last
+fn fill_redeeming_student_by_points(siv: &mut Cursive, event_recorder: &Arc<Mutex<EventRecorder>>, most: bool, skipped: &Rc<Cell<i64>>) {
+    let found = event_recorder.lock().unwrap().student_by_points(most, skipped.get());
+    let found = match found {
+        Ok(None) if skipped.get() > 0 => {
+            skipped.set(0);
+            event_recorder.lock().unwrap().student_by_points(most, 0)
+        },
+        found => found,
+    };
+    let status = match found {
+        Ok(Some((name, points))) => {
+            skipped.set(skipped.get() + 1);
+            let order = if most { "Most points" } else { "Fewest points" };
+            siv.call_on_name("redeeming_student_query", |v: &mut EditView| {
+                v.set_content(name.clone());
+            });
+            siv.call_on_name("redeeming_student_matches", |v: &mut SelectView| {
+                v.clear();
+                v.add_item_str(name.clone());
+            });
+            siv.focus_name("redeeming_date_edit").unwrap();
+            format!("{} #{}: {} ({} points); press again for the next", order, skipped.get(), name, points)
+        },
+        Ok(None) => "No enrolled students".to_string(),
+        Err(e) => format!("Database error: {}", e),
+    };
+    siv.call_on_name("redeeming_status", |v: &mut TextView| {
+        v.set_content(status);
+    });
+}
+
+const REDEEMING_PAGE_SIZE: usize = 15;
+// the ID, category, and recorder columns start out this wide and widen to fit what is listed
+const REDEEMING_ID_WIDTH: usize = 4;
+const REDEEMING_CATEGORY_WIDTH: usize = 10;
+const REDEEMING_DATE_WIDTH: usize = 20;
+const REDEEMING_SATISFACTORY_WIDTH: usize = 11;
+const REDEEMING_RECORDER_WIDTH: usize = 12;
+
+/// Widths of the columns of the redeeming chooser that depend on its events, so that long
+/// category names and recorders are not cut off
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct RedeemingWidths {
+    id: usize,
+    category: usize,
+    recorder: usize,
+}
+
+impl RedeemingWidths {
+    /// Fits the columns to all of the rows, not only the visible ones, so that they keep their
+    /// widths while filtering and paging
+    fn fit(rows: &[EventRow]) -> RedeemingWidths {
+        RedeemingWidths {
+            id: rows.iter()
+                .map(|row| row.db_id.to_string().len())
+                .fold(REDEEMING_ID_WIDTH, usize::max),
+            category: rows.iter()
+                .map(|row| row.category.chars().count())
+                .fold(REDEEMING_CATEGORY_WIDTH, usize::max),
+            recorder: rows.iter()
+                .map(|row| row.recorder.as_deref().map_or(0, |r| r.chars().count()))
+                .fold(REDEEMING_RECORDER_WIDTH, usize::max),
+        }
+    }
+}
+
+/// The events shown by the redeeming chooser, shared by its callbacks
+struct RedeemingChooser {
+    rows: RefCell<Vec<EventRow>>,
+    // selected states, parallel to the rows, are kept here so that changes survive flipping
+    // between pages and filtering; rows deleted from the database are removed from both
+    states: RefCell<Vec<Satisfaction>>,
+    page: Cell<usize>,
+    filter: RefCell<String>,
+    widths: RedeemingWidths,
+}
+
+impl RedeemingChooser {
+    fn new(rows: Vec<EventRow>) -> RedeemingChooser {
+        let states = rows.iter().map(|row| row.satisfactory).collect();
+        let widths = RedeemingWidths::fit(&rows);
+        RedeemingChooser {
+            rows: RefCell::new(rows),
+            states: RefCell::new(states),
+            page: Cell::new(0),
+            filter: RefCell::new(String::new()),
+            widths: widths,
+        }
+    }
+
+    /// Indices of the rows whose category and time match the filter
+    fn visible_rows(&self) -> Vec<usize> {
+        let filter = self.filter.borrow();
+        if filter.is_empty() {
+            return (0..self.rows.borrow().len()).collect();
+        }
+        let matcher = SkimMatcherV2::default();
+        self.rows.borrow().iter()
+            .enumerate()
+            .filter(|(_, row)| {
+                let text = format!("{} {}", row.category, row.first_entered.format("%H:%M"));
+                matcher.fuzzy_match(&text, &filter).is_some()
+            })
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn page_count(&self) -> usize {
+        self.visible_rows().len().div_ceil(REDEEMING_PAGE_SIZE).max(1)
+    }
+}
+
+fn build_redeeming_dialog_choose(student_name: &str, chosen_date: Date<Local>, rows: Vec<EventRow>, students: Rc<Vec<Student>>, categories: Rc<Vec<Category>>, picker: Arc<Mutex<StudentPicker>>, event_recorder: Arc<Mutex<EventRecorder>>) -> Dialog {
+    let chooser = Rc::new(RedeemingChooser::new(rows));
+    let page_count = chooser.page_count();
+    let read_only = event_recorder.lock().unwrap().read_only();
+    let chooser_for_filter = Rc::clone(&chooser);
+    let recorder_for_filter = Arc::clone(&event_recorder);
+    let chooser_for_clear = Rc::clone(&chooser);
+    let recorder_for_clear = Arc::clone(&event_recorder);
+    let chooser_for_prev = Rc::clone(&chooser);
+    let recorder_for_prev = Arc::clone(&event_recorder);
+    let chooser_for_next = Rc::clone(&chooser);
+    let recorder_for_next = Arc::clone(&event_recorder);
+    let chooser_for_check = Rc::clone(&chooser);
+    let chooser_for_uncheck = Rc::clone(&chooser);
+    Dialog::around(
+        LinearLayout::vertical()
+        .child(LinearLayout::horizontal()
+            .child(TextView::new("Filter: "))
             .child(
-                TextView::new(format!("{}", first_entered.format("%H:%M %F")))
-                .fixed_width(date_width)
+                EditView::new()
+                // narrow the rows every time the filter changes
+                .on_edit(move |siv: &mut Cursive, query: &str, _cursor: usize| {
+                    *chooser_for_filter.filter.borrow_mut() = query.to_string();
+                    chooser_for_filter.page.set(0);
+                    show_redeeming_page(siv, &chooser_for_filter, &recorder_for_filter);
+                    // keep typing in the filter rather than jumping to the rows
+                    siv.focus_name("redeeming_filter_edit").unwrap();
+                })
+                .with_name("redeeming_filter_edit")
+                .fixed_width(20)
             )
             .child(DummyView)
             .child(
-                Checkbox::new()
-                .with_checked(sat)
-                .on_change(move |siv: &mut Cursive, _val: bool| {
-                    let next_i = i + 1;
-                    if next_i >= rows_len {
-                        siv.focus_name("redeeming_submit_button").unwrap();
-                    } else {
-                        siv.focus_name(&*format!("redeeming_checkbox_{}", next_i)).unwrap();
-                    }
+                Button::new("Clear", move |siv: &mut Cursive| {
+                    siv.call_on_name("redeeming_filter_edit", |v: &mut EditView| {
+                        v.set_content("");
+                    });
+                    chooser_for_clear.filter.borrow_mut().clear();
+                    chooser_for_clear.page.set(0);
+                    show_redeeming_page(siv, &chooser_for_clear, &recorder_for_clear);
                 })
-                .fixed_width(satisfactory_width)
-                .with_name(format!("redeeming_checkbox_{}", i))
             )
-        );
-    }
-    Dialog::around(
-        LinearLayout::vertical()
+        )
+        .child(DummyView)
         .child(LinearLayout::horizontal()
             .child(
                 TextView::new("ID")
                 .h_align(HAlign::Right)
-                .fixed_width(id_width)
+                .fixed_width(chooser.widths.id)
             )
             .child(DummyView)
             .child(
                 TextView::new("Category")
-                .fixed_width(category_width)
+                .fixed_width(chooser.widths.category)
             )
             .child(DummyView)
             .child(
                 TextView::new("Date")
-                .fixed_width(date_width)
+                .fixed_width(REDEEMING_DATE_WIDTH)
+            )
+            .child(DummyView)
+            .child(
+                TextView::new("By")
+                .fixed_width(chooser.widths.recorder)
             )
             .child(DummyView)
             .child(
                 TextView::new("?")
-                .fixed_width(satisfactory_width)
+                .fixed_width(REDEEMING_SATISFACTORY_WIDTH)
             )
         )
         .child(DummyView)
-        .child(data.full_height().scrollable())
+        .child(
+            build_redeeming_page(&chooser, &event_recorder)
+            .with_name("redeeming_page")
+            .full_height()
+            .scrollable()
+        )
         .child(DummyView)
+        .child(LinearLayout::horizontal()
+            .child(
+                Button::new("Prev", move |siv: &mut Cursive| {
+                    if chooser_for_prev.page.get() > 0 {
+                        chooser_for_prev.page.set(chooser_for_prev.page.get() - 1);
+                        show_redeeming_page(siv, &chooser_for_prev, &recorder_for_prev);
+                    }
+                })
+                .with_name("redeeming_prev_button")
+            )
+            .child(DummyView)
+            .child(
+                TextView::new(format!("Page 1 of {}", page_count))
+                .with_name("redeeming_page_label")
+            )
+            .child(DummyView)
+            .child(
+                Button::new("Next", move |siv: &mut Cursive| {
+                    if chooser_for_next.page.get() + 1 < chooser_for_next.page_count() {
+                        chooser_for_next.page.set(chooser_for_next.page.get() + 1);
+                        show_redeeming_page(siv, &chooser_for_next, &recorder_for_next);
+                    }
+                })
+                .with_name("redeeming_next_button")
+            )
+        )
+        .child(LinearLayout::horizontal()
+            .child(
+                Button::new("Check all", move |siv: &mut Cursive| {
+                    set_all_redeeming_states(siv, &chooser_for_check, Satisfaction::Satisfactory);
+                })
+            )
+            .child(DummyView)
+            .child(
+                Button::new("Uncheck all", move |siv: &mut Cursive| {
+                    set_all_redeeming_states(siv, &chooser_for_uncheck, Satisfaction::Unsatisfactory);
+                })
+            )
+        )
         .child(
             Button::new("Submit", move |siv: &mut Cursive| {
-                let changes: Vec<(bool, i32)> = rows.iter().enumerate()
-                    .filter_map(|(i, row)| {
-                        let db_id: i32 = row.get(0);
-                        let original: bool = row.get(3);
-                        let submitted: bool = siv.find_name::<ResizedView<Checkbox>>(&*format!("redeeming_checkbox_{}", i)).unwrap().get_inner().is_checked();
-                        if original != submitted {
-                            Some((submitted, db_id))
+                // rows hidden by the filter are included, so no change is lost
+                let changes: Vec<(Satisfaction, i32)> = chooser.rows.borrow().iter().zip(chooser.states.borrow().iter())
+                    .filter_map(|(row, submitted)| {
+                        if row.satisfactory != *submitted {
+                            Some((*submitted, row.db_id))
                         } else {
                             None
                         }
@@ -742,8 +3033,14 @@ fn build_redeeming_dialog_choose(student_name: &str, chosen_date: Date<Local>, r
                 siv.call_on_name("redeeming_chooser_status_msg", |v: &mut TextView| {
                     v.set_content("Updating database");
                 });
-                match event_recorder.lock().unwrap().change_events(&changes) {
-                    Ok(()) => {
+                let changed = event_recorder.lock().unwrap().change_events(&changes);
+                match changed {
+                    Ok((updated, missing)) => {
+                        let mut msg = format!("Updated {} event{}", updated, if updated == 1 { "" } else { "s" });
+                        if !missing.is_empty() {
+                            let ids: Vec<String> = missing.iter().map(|id| id.to_string()).collect();
+                            msg.push_str(&format!("\nThese events no longer exist and were not updated: {}", ids.join(", ")));
+                        }
                         siv.pop_layer();
                         siv.add_layer(build_main_menu(
                             Rc::clone(&students),
@@ -751,7 +3048,7 @@ fn build_redeeming_dialog_choose(student_name: &str, chosen_date: Date<Local>, r
                             Arc::clone(&picker),
                             Arc::clone(&event_recorder),
                         ));
-                        siv.add_layer(Dialog::info("Database changes recorded"))
+                        siv.add_layer(Dialog::info(msg))
                     },
                     Err(e) => {
                         match e.as_db_error() {
@@ -769,6 +3066,7 @@ fn build_redeeming_dialog_choose(student_name: &str, chosen_date: Date<Local>, r
                     }
                 }
             })
+            .with_enabled(!read_only)
             .with_name("redeeming_submit_button")
         )
         .child(
@@ -778,3 +3076,195 @@ fn build_redeeming_dialog_choose(student_name: &str, chosen_date: Date<Local>, r
     )
     .title(format!("{} ({})", student_name, chosen_date))
 }
+
+/// Replaces the displayed page of events in the redeeming chooser and focuses its first selector
+fn show_redeeming_page(siv: &mut Cursive, chooser: &Rc<RedeemingChooser>, event_recorder: &Arc<Mutex<EventRecorder>>) {
+    let new_page = build_redeeming_page(chooser, event_recorder);
+    siv.call_on_name("redeeming_page", |v: &mut LinearLayout| {
+        *v = new_page;
+    });
+    let page = chooser.page.get();
+    siv.call_on_name("redeeming_page_label", |v: &mut TextView| {
+        v.set_content(format!("Page {} of {}", page + 1, chooser.page_count()));
+    });
+    let first = chooser.visible_rows().get(page * REDEEMING_PAGE_SIZE).copied();
+    if first.is_none_or(|i| siv.focus_name(&*format!("redeeming_state_{}", i)).is_err()) {
+        siv.focus_name("redeeming_submit_button").unwrap();
+    }
+}
+
+fn build_redeeming_page(chooser: &Rc<RedeemingChooser>, event_recorder: &Arc<Mutex<EventRecorder>>) -> LinearLayout {
+    let mut data = LinearLayout::vertical();
+    if chooser.rows.borrow().is_empty() {
+        data.add_child(TextView::new("No events on this day"));
+        return data;
+    }
+    let visible = chooser.visible_rows();
+    if visible.is_empty() {
+        data.add_child(TextView::new("No events match the filter"));
+        return data;
+    }
+    let read_only = event_recorder.lock().unwrap().read_only();
+    let page_start = chooser.page.get() * REDEEMING_PAGE_SIZE;
+    let page_end = (page_start + REDEEMING_PAGE_SIZE).min(visible.len());
+    let rows = chooser.rows.borrow();
+    for (position, &i) in visible.iter().enumerate().take(page_end).skip(page_start) {
+        let row = &rows[i];
+        // the selector to move to once this one is chosen
+        let next_focus = if position + 1 >= visible.len() {
+            "redeeming_submit_button".to_string()
+        } else if position + 1 >= page_end {
+            "redeeming_next_button".to_string()
+        } else {
+            format!("redeeming_state_{}", visible[position + 1])
+        };
+        // moving up from the first row of a page goes back to the filter above the rows
+        let prev_focus = if position == page_start {
+            "redeeming_filter_edit".to_string()
+        } else {
+            format!("redeeming_state_{}", visible[position - 1])
+        };
+        let next_focus_for_key = next_focus.clone();
+        let next_focus_for_arrow = next_focus.clone();
+        let prev_focus_for_arrow = prev_focus.clone();
+        let chooser_for_change = Rc::clone(chooser);
+        let chooser_for_toggle = Rc::clone(chooser);
+        let chooser_for_delete = Rc::clone(chooser);
+        let recorder_for_delete = Arc::clone(event_recorder);
+        let db_id = row.db_id;
+        let description = format!("{} at {}", row.category, row.first_entered.format("%H:%M"));
+        data.add_child(LinearLayout::horizontal()
+            .child(
+                TextView::new(format!("{}", row.db_id))
+                .h_align(HAlign::Right)
+                .fixed_width(chooser.widths.id)
+            )
+            .child(DummyView)
+            .child(
+                TextView::new(row.category.to_string())
+                .fixed_width(chooser.widths.category)
+            )
+            .child(DummyView)
+            .child(
+                TextView::new(format!("{}", row.first_entered.format("%H:%M %F")))
+                .fixed_width(REDEEMING_DATE_WIDTH)
+            )
+            .child(DummyView)
+            .child(
+                TextView::new(row.recorder.clone().unwrap_or_default())
+                .fixed_width(chooser.widths.recorder)
+            )
+            .child(DummyView)
+            .child(
+                OnEventView::new(
+                    build_satisfaction_select()
+                    .selected(match chooser.states.borrow()[i] {
+                        Satisfaction::Unsatisfactory => 0,
+                        Satisfaction::Satisfactory => 1,
+                        Satisfaction::Pending => 2,
+                    })
+                    .popup()
+                    .on_submit(move |siv: &mut Cursive, val: &Satisfaction| {
+                        chooser_for_change.states.borrow_mut()[i] = *val;
+                        siv.focus_name(&next_focus).unwrap();
+                    })
+                    .with_name(format!("redeeming_state_{}", i))
+                    .fixed_width(REDEEMING_SATISFACTORY_WIDTH)
+                )
+                // move between rows without opening the selector, and change a row in place
+                .on_event('j', move |siv: &mut Cursive| {
+                    siv.focus_name(&next_focus_for_key).unwrap();
+                })
+                .on_event('k', move |siv: &mut Cursive| {
+                    siv.focus_name(&prev_focus).unwrap();
+                })
+                .on_event(Key::Down, move |siv: &mut Cursive| {
+                    siv.focus_name(&next_focus_for_arrow).unwrap();
+                })
+                .on_event(Key::Up, move |siv: &mut Cursive| {
+                    siv.focus_name(&prev_focus_for_arrow).unwrap();
+                })
+                .on_event(' ', move |siv: &mut Cursive| {
+                    toggle_redeeming_state(siv, &chooser_for_toggle, i);
+                })
+            )
+            .child(DummyView)
+            .child(
+                Button::new("Delete", move |siv: &mut Cursive| {
+                    let chooser_for_confirm = Rc::clone(&chooser_for_delete);
+                    let recorder_for_confirm = Arc::clone(&recorder_for_delete);
+                    siv.add_layer(
+                        Dialog::text(format!("Delete event {} ({})?", db_id, description))
+                        .button("Delete", move |confirm_siv: &mut Cursive| {
+                            confirm_siv.pop_layer();
+                            delete_redeeming_row(confirm_siv, db_id, &chooser_for_confirm, &recorder_for_confirm);
+                        })
+                        .dismiss_button("Cancel")
+                    );
+                })
+                .with_enabled(!read_only)
+            )
+        );
+    }
+    data
+}
+
+/// Moves the row's selector on to the next satisfaction, wrapping around, without moving focus
+fn toggle_redeeming_state(siv: &mut Cursive, chooser: &Rc<RedeemingChooser>, i: usize) {
+    let toggled = siv.call_on_name(&format!("redeeming_state_{}", i), |v: &mut SelectView<Satisfaction>| {
+        let next = (v.selected_id().unwrap_or(0) + 1) % v.len();
+        v.set_selection(next);
+        v.selection().map(|s| *s)
+    });
+    if let Some(Some(satisfaction)) = toggled {
+        chooser.states.borrow_mut()[i] = satisfaction;
+    }
+}
+
+/// Sets every row that the filter shows, on every page, to the same state; the submitted changes
+/// are still worked out against the states in the database
+fn set_all_redeeming_states(siv: &mut Cursive, chooser: &Rc<RedeemingChooser>, satisfaction: Satisfaction) {
+    let visible = chooser.visible_rows();
+    for &i in &visible {
+        chooser.states.borrow_mut()[i] = satisfaction;
+        // rows on other pages pick up the state when their page is shown
+        siv.call_on_name(&format!("redeeming_state_{}", i), |v: &mut SelectView<Satisfaction>| {
+            let index = v.iter().position(|(_, s)| *s == satisfaction);
+            if let Some(index) = index {
+                v.set_selection(index);
+            }
+        });
+    }
+    siv.call_on_name("redeeming_chooser_status_msg", |v: &mut TextView| {
+        v.set_content(format!("Set {} event{} to {}", visible.len(), if visible.len() == 1 { "" } else { "s" }, satisfaction));
+    });
+}
+
+/// Deletes the event from the database and removes its row from the redeeming chooser
+fn delete_redeeming_row(siv: &mut Cursive, db_id: i32, chooser: &Rc<RedeemingChooser>, event_recorder: &Arc<Mutex<EventRecorder>>) {
+    let deleted = event_recorder.lock().unwrap().delete_event(db_id);
+    let msg = match deleted {
+        Ok(0) => format!("Event {} no longer exists", db_id),
+        Ok(_) => format!("Deleted event {}; refresh the summary to update points", db_id),
+        Err(e) => {
+            siv.call_on_name("redeeming_chooser_status_msg", |v: &mut TextView| {
+                v.set_content(format!("Database error: {}", e));
+            });
+            return;
+        }
+    };
+    let position = chooser.rows.borrow().iter().position(|row| row.db_id == db_id);
+    if let Some(i) = position {
+        chooser.rows.borrow_mut().remove(i);
+        chooser.states.borrow_mut().remove(i);
+    }
+    // deleting the only row on the last page moves back a page
+    let page_count = chooser.page_count();
+    if chooser.page.get() >= page_count {
+        chooser.page.set(page_count - 1);
+    }
+    show_redeeming_page(siv, chooser, event_recorder);
+    siv.call_on_name("redeeming_chooser_status_msg", |v: &mut TextView| {
+        v.set_content(msg);
+    });
+}
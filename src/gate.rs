@@ -1,17 +1,116 @@
-use std::error::Error;
-use std::fs::File;
+use std::fs::{self, File};
 use std::ffi::OsString;
 use std::io;
 use std::io::{Write};
+use std::path::Path;
 
 use encoding_rs::UTF_16LE;
 use encoding_rs_io::DecodeReaderBytesBuilder;
 
-use crate::model::{Roster};
+use crate::back::{MAX_NAME_CHARS, MAX_SECTION_CHARS, MAX_UB_ID_CHARS, MAX_USERNAME_CHARS};
+use crate::error::AppError;
+use crate::model::{DetailedSummary, ExportIdentifier, Labels, Roster, SkippedRow, SummaryRow};
 
-pub fn read_roster(path: OsString) -> Result<Roster, Box<dyn Error>> {
-    // https://stackoverflow.com/a/53833111
+/// Fraction of malformed rows above which a roster is rejected outright
+const MAX_SKIPPED_FRACTION: f64 = 0.5;
+
+/// Layouts of roster files that can be imported
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RosterFormat {
+    // tab separated UTF-16 from Blackboard, with columns for last name, first name, username, and
+    // UB ID; a column with the header "Section" is optional
+    Blackboard,
+    // comma separated UTF-8 from Canvas, with columns found by their header names; the "Section"
+    // column is optional
+    Canvas,
+}
+
+/// A student's UB ID, full name, username, and section, if any, as read from a roster
+struct RosterEntry {
+    line: u64,
+    ub_id: String,
+    name: String,
+    username: String,
+    section: Option<String>,
+}
+
+impl RosterEntry {
+    fn new(record: &csv::StringRecord, ub_id: String, name: String, username: String, section: Option<&str>) -> RosterEntry {
+        RosterEntry {
+            line: record.position().map_or(0, |p| p.line()),
+            ub_id: ub_id,
+            name: name,
+            username: username,
+            // a blank section means the same as none
+            section: section.filter(|s| !s.is_empty()).map(|s| s.to_string()),
+        }
+    }
+
+    /// Describes each field too long for the students table
+    fn overlong_fields(&self) -> Vec<String> {
+        let fields = [
+            ("UB ID", self.ub_id.as_str(), MAX_UB_ID_CHARS),
+            ("name", self.name.as_str(), MAX_NAME_CHARS),
+            ("username", self.username.as_str(), MAX_USERNAME_CHARS),
+            ("section", self.section.as_deref().unwrap_or_default(), MAX_SECTION_CHARS),
+        ];
+        fields.iter()
+            .filter(|(_, value, max)| value.chars().count() > *max)
+            .map(|(field, value, max)| format!("line {}: {} \"{}\" is longer than {} characters", self.line, field, value, max))
+            .collect()
+    }
+}
+
+pub fn read_roster(path: OsString, format: RosterFormat) -> Result<Roster, AppError> {
     let fh = File::open(path)?;
+    let (entries, skipped) = match format {
+        RosterFormat::Blackboard => read_blackboard_rows(fh)?,
+        RosterFormat::Canvas => read_canvas_rows(fh)?,
+    };
+    let total_rows = entries.len() + skipped.len();
+    if skipped.len() as f64 > MAX_SKIPPED_FRACTION * total_rows as f64 {
+        return Err(AppError::Roster(format!(
+            "{} of {} roster rows were malformed (first at line {}); are the columns in the expected order?",
+            skipped.len(),
+            total_rows,
+            skipped[0].line
+        )));
+    }
+    // report every overlong field at once rather than failing partway through the import
+    let overlong: Vec<String> = entries.iter()
+        .flat_map(|e| e.overlong_fields())
+        .collect();
+    if !overlong.is_empty() {
+        return Err(AppError::Roster(format!("Roster values too long for the database: {}", overlong.join("; "))));
+    }
+    let mut ub_ids = vec![];
+    let mut names = vec![];
+    let mut usernames = vec![];
+    let mut sections = vec![];
+    for entry in entries {
+        ub_ids.push(entry.ub_id);
+        names.push(entry.name);
+        usernames.push(entry.username);
+        sections.push(entry.section);
+    }
+    let roster = Roster::new(
+        ub_ids,
+        names,
+        usernames,
+        skipped,
+    ).with_sections(sections);
+    Ok(roster)
+}
+
+fn skipped_row(record: &csv::StringRecord, separator: &str) -> SkippedRow {
+    SkippedRow {
+        line: record.position().map_or(0, |p| p.line()),
+        contents: record.iter().collect::<Vec<_>>().join(separator),
+    }
+}
+
+fn read_blackboard_rows(fh: File) -> Result<(Vec<RosterEntry>, Vec<SkippedRow>), AppError> {
+    // https://stackoverflow.com/a/53833111
     let transcoded = DecodeReaderBytesBuilder::new()
         .encoding(Some(UTF_16LE))
         .build(fh);
@@ -20,57 +119,297 @@ pub fn read_roster(path: OsString) -> Result<Roster, Box<dyn Error>> {
         .flexible(true)
         .trim(csv::Trim::All)
         .from_reader(transcoded);
-    let mut ub_ids = vec![];
-    let mut names = vec![];
-    let mut usernames = vec![];
+    // other columns, such as the last access, can follow the UB ID, so the section goes by name
+    let section_column = rdr.headers()?.iter().position(|h| h == "Section");
+    let mut entries = vec![];
+    let mut skipped = vec![];
     for r in rdr.records() {
         let res = r?;
-        let cur_ub_id = match res.get(3) {
-            Some(a) => a,
-            None => continue,
-        };
-        let last_name = match res.get(0) {
-            Some(a) => a,
-            None => continue,
+        let (last_name, first_name, username, cur_ub_id) = match (res.get(0), res.get(1), res.get(2), res.get(3)) {
+            (Some(a), Some(b), Some(c), Some(d)) => (a, b, c, d),
+            _ => {
+                skipped.push(skipped_row(&res, "\t"));
+                continue;
+            }
         };
-        let first_name = match res.get(1) {
-            Some(a) => a,
-            None => continue,
-        };
-        let username = match res.get(2) {
-            Some(a) => a,
-            None => continue,
-        };
-        ub_ids.push(format!("{}", cur_ub_id));
-        names.push(format!("{} {}", first_name, last_name));
-        usernames.push(format!("{}", username));
-        //println!("{:?}", res);
+        entries.push(RosterEntry::new(&res, format!("{}", cur_ub_id), format!("{} {}", first_name, last_name), format!("{}", username), section_column.and_then(|i| res.get(i))));
     }
-    let roster = Roster::new(
-        ub_ids,
-        names,
-        usernames,
-    );
-    Ok(roster)
+    Ok((entries, skipped))
+}
+
+fn read_canvas_rows(fh: File) -> Result<(Vec<RosterEntry>, Vec<SkippedRow>), AppError> {
+    // without an explicit encoding, a leading byte order mark is dropped and UTF-8 passes through
+    let decoded = DecodeReaderBytesBuilder::new()
+        .build(fh);
+    let mut rdr = csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(decoded);
+    let headers = rdr.headers()?.clone();
+    let column = |name: &str| headers.iter()
+        .position(|h| h == name)
+        .ok_or_else(|| AppError::Roster(format!("Canvas roster has no \"{}\" column", name)));
+    let name_column = column("Student")?;
+    let ub_id_column = column("SIS User ID")?;
+    let username_column = column("SIS Login ID")?;
+    let section_column = headers.iter().position(|h| h == "Section");
+    let mut entries = vec![];
+    let mut skipped = vec![];
+    for r in rdr.records() {
+        let res = r?;
+        match (res.get(name_column), res.get(ub_id_column), res.get(username_column)) {
+            // gradebook exports carry a row of maximum points and Canvas's own test student
+            (Some("Points Possible"), _, _) | (Some("Student, Test"), _, _) => continue,
+            (Some(student), Some(ub_id), Some(username)) if !student.is_empty() && !ub_id.is_empty() => {
+                let section = section_column.and_then(|i| res.get(i));
+                entries.push(RosterEntry::new(&res, ub_id.to_string(), canvas_full_name(student), username.to_string(), section));
+            },
+            _ => skipped.push(skipped_row(&res, ",")),
+        }
+    }
+    Ok((entries, skipped))
 }
 
-pub fn export_summary(rows: Vec<postgres::Row>, outfile: &mut File) -> Result<(), io::Error> {
-    let p1_max = rows.iter().map(|a| a.get(1)).fold(i64::MIN, |a, b| a.max(b));
-    let p2_max = rows.iter().map(|a| a.get(2)).fold(i64::MIN, |a, b| a.max(b));
-    let p3_max = rows.iter().map(|a| a.get(3)).fold(i64::MIN, |a, b| a.max(b));
+/// Turns Canvas's "Last, First" into "First Last", as names are stored
+fn canvas_full_name(student: &str) -> String {
+    match student.split_once(',') {
+        Some((last_name, first_name)) => format!("{} {}", first_name.trim(), last_name.trim()),
+        None => student.to_string(),
+    }
+}
+
+/// Text encodings that exports can be written in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportEncoding {
+    Utf8,
+    // with a byte order mark, like the rosters that Blackboard exports
+    Utf16Le,
+}
+
+/// Writes the text to the output in the given encoding
+fn write_encoded<W: Write>(outfile: &mut W, text: &str, encoding: ExportEncoding) -> Result<(), io::Error> {
+    match encoding {
+        ExportEncoding::Utf8 => outfile.write_all(text.as_bytes()),
+        ExportEncoding::Utf16Le => {
+            // encoding_rs only decodes UTF-16 (its encoder for UTF-16 produces UTF-8, as the
+            // Encoding Standard requires), so the code units are written out directly
+            let mut bytes = vec![0xFF, 0xFE];
+            for unit in text.encode_utf16() {
+                bytes.extend_from_slice(&unit.to_le_bytes());
+            }
+            outfile.write_all(&bytes)
+        }
+    }
+}
+
+pub fn export_summary<W: Write>(rows: Vec<SummaryRow>, identifier: ExportIdentifier, encoding: ExportEncoding, outfile: &mut W) -> Result<(), io::Error> {
+    // with no students there is no maximum, and no points can have been earned
+    let period_max = |i: usize| rows.iter().map(|a| a.periods[i]).max().unwrap_or(0);
+    let p1_max = period_max(0);
+    let p2_max = period_max(1);
+    let p3_max = period_max(2);
     // Note that column identifiers are hard-coded here; a more flexible approach might allow for
     // changing them
     let p1_header = format!("Participation 1 [Total Pts: {} Score] |1576192", p1_max);
     let p2_header = format!("Participation 2 [Total Pts: {} Score] |1576193", p2_max);
     let p3_header = format!("Participation 3 [Total Pts: {} Score] |1576194", p3_max);
-    let header_line = format!("\"Username\"\t\"{}\"\t\"{}\"\t\"{}\"\n", p1_header, p2_header, p3_header);
-    outfile.write_all(header_line.as_bytes())?;
+    let mut contents = format!("\"{}\"\t\"{}\"\t\"{}\"\t\"{}\"\n", identifier.header(), p1_header, p2_header, p3_header);
+    for row in rows {
+        contents.push_str(&format!("{}\n", row.to_line(&identifier)));
+    }
+    write_encoded(outfile, &contents, encoding)
+}
+
+/// The line naming the term that review exports start with, so that exports from different
+/// courses are not mixed up; nothing when the schema has no term label
+fn term_line(term_label: Option<&str>) -> String {
+    match term_label {
+        Some(label) => format!("\"Term: {}\"\n", label.replace('"', "\"\"")),
+        None => String::new(),
+    }
+}
+
+/// Exports each student's satisfactory contributions over a range of dates, described by
+/// `range_label` in the header, as from `EventRecorder::get_range_summary`
+pub fn export_range_summary<W: Write>(rows: Vec<SummaryRow>, range_label: &str, term_label: Option<&str>, identifier: ExportIdentifier, encoding: ExportEncoding, outfile: &mut W) -> Result<(), io::Error> {
+    let mut contents = term_line(term_label);
+    contents.push_str(&format!("\"{}\"\t\"Participation {}\"\n", identifier.header(), range_label));
     for row in rows {
-        let username: String = row.get(0);
-        let p1: i64 = row.get(1);
-        let p2: i64 = row.get(2);
-        let p3: i64 = row.get(3);
-        outfile.write_all(format!("\"{}\"\t{}\t{}\t{}\n", username, p1, p2, p3).as_bytes())?;
+        contents.push_str(&format!("{}\n", row.to_line(&identifier)));
+    }
+    write_encoded(outfile, &contents, encoding)
+}
+
+/// Exports the summary broken down by category, for review rather than for uploading grades
+pub fn export_detailed_summary<W: Write>(summary: DetailedSummary, term_label: Option<&str>, identifier: ExportIdentifier, encoding: ExportEncoding, outfile: &mut W) -> Result<(), io::Error> {
+    let mut headers = vec![identifier.header().to_string()];
+    for period in 1..=3 {
+        headers.push(format!("Participation {} total", period));
+        for category in &summary.categories {
+            headers.push(format!("Participation {} {}", period, category));
+        }
+    }
+    let header_line = headers.iter()
+        .map(|h| format!("\"{}\"", h))
+        .collect::<Vec<_>>()
+        .join("\t");
+    let mut contents = term_line(term_label);
+    contents.push_str(&format!("{}\n", header_line));
+    for row in summary.rows {
+        contents.push_str(&format!("{}\n", row.to_line(&identifier)));
+    }
+    write_encoded(outfile, &contents, encoding)
+}
+
+/// Reads the labels file at `path`, keeping the usual wording when there is no such file
+pub fn read_labels(path: &Path) -> Result<Labels, AppError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => parse_labels(&contents),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Labels::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Parses labels given as TOML strings; labels left out keep their usual wording, and unknown
+/// keys are rejected so that a misspelled label is not silently ignored
+fn parse_labels(contents: &str) -> Result<Labels, AppError> {
+    let value: toml::Value = contents.parse()?;
+    let table = value.as_table()
+        .ok_or_else(|| AppError::Config("labels must be a table of strings".to_string()))?;
+    let mut labels = Labels::default();
+    for (key, value) in table {
+        let text = value.as_str()
+            .ok_or_else(|| AppError::Config(format!("label {} must be a string", key)))?
+            .to_string();
+        match key.as_str() {
+            "satisfactory_header" => labels.satisfactory_header = text,
+            "satisfactory_prompt" => labels.satisfactory_prompt = text,
+            _ => return Err(AppError::Config(format!("unknown label: {}", key))),
+        }
+    }
+    Ok(labels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::process;
+
+    /// Exports the rows and returns what was written
+    fn exported_summary(rows: Vec<SummaryRow>) -> String {
+        let mut out = vec![];
+        export_summary(rows, ExportIdentifier::Username, ExportEncoding::Utf8, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn empty_summary_exports_zero_total_points() {
+        let contents = exported_summary(vec![]);
+        assert_eq!(contents.lines().count(), 1);
+        assert_eq!(contents.matches("[Total Pts: 0 Score]").count(), 3);
+    }
+
+    #[test]
+    fn summary_header_has_highest_points_of_each_period() {
+        let rows = vec![
+            SummaryRow { username: "jdoe".to_string(), ub_id: "12345678".to_string(), periods: vec![3, 0, 1] },
+            SummaryRow { username: "rroe".to_string(), ub_id: "87654321".to_string(), periods: vec![5, 0, 0] },
+        ];
+        let contents = exported_summary(rows);
+        let header = contents.lines().next().unwrap();
+        assert!(header.contains("Participation 1 [Total Pts: 5 Score]"));
+        assert!(header.contains("Participation 2 [Total Pts: 0 Score]"));
+        assert!(header.contains("Participation 3 [Total Pts: 1 Score]"));
+    }
+
+    #[test]
+    fn summary_rows_are_exported_in_order_with_their_points() {
+        let rows = vec![
+            SummaryRow { username: "jdoe".to_string(), ub_id: "12345678".to_string(), periods: vec![3, 0, 1] },
+            SummaryRow { username: String::new(), ub_id: "87654321".to_string(), periods: vec![0, 2, 0] },
+        ];
+        let contents = exported_summary(rows);
+        assert_eq!(contents.lines().skip(1).collect::<Vec<_>>(), vec!["\"jdoe\"\t3\t0\t1", "\"\"\t0\t2\t0"]);
+    }
+
+    #[test]
+    fn pseudonyms_depend_on_the_key() {
+        let row = SummaryRow { username: "jdoe".to_string(), ub_id: "12345678".to_string(), periods: vec![3, 0, 1] };
+        let pseudonym = |key: &[u8]| row.identifier(&ExportIdentifier::Pseudonym(key.to_vec()));
+        assert_eq!(pseudonym(b"secret"), pseudonym(b"secret"));
+        assert_ne!(pseudonym(b"secret"), pseudonym(b"other secret"));
+        assert!(pseudonym(b"secret").starts_with("student-"));
+        assert!(!pseudonym(b"secret").contains("12345678"));
+    }
+
+    #[test]
+    fn range_export_starts_with_the_term_only_when_there_is_one() {
+        let rows = || vec![SummaryRow { username: "jdoe".to_string(), ub_id: "12345678".to_string(), periods: vec![2] }];
+        let mut labeled = vec![];
+        export_range_summary(rows(), "2024-09-01 to 2024-09-07", Some("Fall 2024 LATIN 101"), ExportIdentifier::Username, ExportEncoding::Utf8, &mut labeled).unwrap();
+        let labeled = String::from_utf8(labeled).unwrap();
+        let mut unlabeled = vec![];
+        export_range_summary(rows(), "2024-09-01 to 2024-09-07", None, ExportIdentifier::Username, ExportEncoding::Utf8, &mut unlabeled).unwrap();
+        let unlabeled = String::from_utf8(unlabeled).unwrap();
+        assert_eq!(labeled.lines().next(), Some("\"Term: Fall 2024 LATIN 101\""));
+        assert_eq!(labeled.lines().skip(1).collect::<Vec<_>>(), unlabeled.lines().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn labels_left_out_keep_their_usual_wording() {
+        let labels = parse_labels("satisfactory_header = \"Counts?\"\n").unwrap();
+        assert_eq!(labels.satisfactory_header, "Counts?");
+        assert_eq!(labels.satisfactory_prompt, Labels::default().satisfactory_prompt);
+    }
+
+    #[test]
+    fn misspelled_label_is_rejected() {
+        assert!(parse_labels("satisfactory_heading = \"Counts?\"\n").is_err());
+        assert!(parse_labels("satisfactory_header = 1\n").is_err());
+    }
+
+    #[test]
+    fn roster_failures_tell_missing_files_from_bad_contents() {
+        let path = std::env::temp_dir().join(format!("participation_roster_test_{}.csv", process::id()));
+        let missing = read_roster(path.clone().into_os_string(), RosterFormat::Canvas);
+        assert!(matches!(missing, Err(AppError::Io(_))));
+        fs::write(&path, "Name,ID\nJane Doe,12345678\n").unwrap();
+        let headerless = read_roster(path.clone().into_os_string(), RosterFormat::Canvas);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(headerless, Err(AppError::Roster(_))));
+    }
+
+    #[test]
+    fn canvas_roster_sections_are_read_when_given() {
+        let path = std::env::temp_dir().join(format!("participation_sections_test_{}.csv", process::id()));
+        fs::write(&path, "Student,SIS User ID,SIS Login ID,Section\n\"Doe, Jane\",12345678,jdoe,LAT 101 A\n\"Roe, Rick\",87654321,rroe,\n").unwrap();
+        let with_sections = read_roster(path.clone().into_os_string(), RosterFormat::Canvas);
+        fs::write(&path, "Student,SIS User ID,SIS Login ID\n\"Doe, Jane\",12345678,jdoe\n").unwrap();
+        let without_sections = read_roster(path.clone().into_os_string(), RosterFormat::Canvas);
+        fs::remove_file(&path).unwrap();
+        let with_sections = with_sections.unwrap();
+        assert_eq!(with_sections.section(0), Some("LAT 101 A"));
+        assert_eq!(with_sections.section(1), None);
+        assert_eq!(without_sections.unwrap().section(0), None);
+    }
+
+    #[test]
+    fn blackboard_section_is_found_by_its_header() {
+        let path = std::env::temp_dir().join(format!("participation_blackboard_test_{}.txt", process::id()));
+        let utf16 = |text: &str| {
+            let mut bytes = vec![0xFF, 0xFE];
+            bytes.extend(text.encode_utf16().flat_map(|unit| unit.to_le_bytes()));
+            bytes
+        };
+        fs::write(&path, utf16("Last Name\tFirst Name\tUsername\tStudent ID\tLast Access\tSection\nDoe\tJane\tjdoe\t12345678\t2024-09-01\tLAT 101 A\n")).unwrap();
+        let with_section = read_roster(path.clone().into_os_string(), RosterFormat::Blackboard);
+        fs::write(&path, utf16("Last Name\tFirst Name\tUsername\tStudent ID\tLast Access\nDoe\tJane\tjdoe\t12345678\t2024-09-01\n")).unwrap();
+        let without_section = read_roster(path.clone().into_os_string(), RosterFormat::Blackboard);
+        fs::remove_file(&path).unwrap();
+        let with_section = with_section.unwrap();
+        assert_eq!(with_section.iter().next().unwrap().1, "Jane Doe");
+        assert_eq!(with_section.section(0), Some("LAT 101 A"));
+        assert_eq!(without_section.unwrap().section(0), None);
     }
-    Ok(())
 }
@@ -1,59 +1,731 @@
 mod back;
+mod error;
 mod front;
 mod gate;
 mod model;
 
 use std::env;
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
 use std::process;
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::NaiveTime;
+
+use crate::error::AppError;
 
 fn main() -> () {
-    let schema = "real";
-    let roster = match env::args_os().nth(1) {
-        Some(path) => {
-            match gate::read_roster(path) {
-                Ok(r) => Some(r),
-                Err(e) => {
-                    println!("Error in reading roster:");
-                    println!("{:?}", e);
-                    process::exit(1);
+    // looked for here rather than in Args so that argument errors can be reported as JSON too
+    let json_errors = env::args_os().any(|arg| arg == "--json-errors");
+    // run returns only after the user interface (if started) has restored the terminal, so the
+    // error message will not be lost in the alternate screen
+    if let Err(failure) = run() {
+        if json_errors {
+            println!("{}", failure.to_json());
+        } else {
+            println!("{}", failure.message);
+        }
+        process::exit(failure.kind.exit_code());
+    }
+}
+
+/// Kinds of startup failure, each with its own exit code so that scripts can tell them apart
+#[derive(Clone, Copy, Debug)]
+enum FailureKind {
+    // bad arguments, export file problems, and errors in the user interface
+    Other,
+    Roster,
+    Connect,
+    // a query failed or the schema is not as expected
+    Database,
+}
+
+impl FailureKind {
+    fn exit_code(&self) -> i32 {
+        match self {
+            FailureKind::Other => 1,
+            FailureKind::Roster => 2,
+            FailureKind::Connect => 3,
+            FailureKind::Database => 4,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            FailureKind::Other => "other",
+            FailureKind::Roster => "roster",
+            FailureKind::Connect => "connect",
+            FailureKind::Database => "database",
+        }
+    }
+}
+
+/// Why the program stopped early
+struct Failure {
+    kind: FailureKind,
+    message: String,
+}
+
+impl Failure {
+    fn new(kind: FailureKind, message: String) -> Failure {
+        Failure { kind: kind, message: message }
+    }
+
+    /// Describes the failure as a single line of JSON
+    fn to_json(&self) -> String {
+        format!(
+            "{{\"error\":\"{}\",\"exit_code\":{},\"message\":{}}}",
+            self.kind.name(),
+            self.kind.exit_code(),
+            json_string(&self.message)
+        )
+    }
+}
+
+impl From<AppError> for Failure {
+    fn from(e: AppError) -> Failure {
+        match e {
+            AppError::Db(e) => Failure::new(FailureKind::Database, format!("Database error:\n{}", e)),
+            AppError::Roster(msg) => Failure::new(FailureKind::Roster, format!("Error in reading roster:\n{}", msg)),
+            AppError::Io(e) => Failure::new(FailureKind::Other, format!("File error:\n{}", e)),
+            AppError::Config(msg) => Failure::new(FailureKind::Other, msg),
+        }
+    }
+}
+
+impl From<Box<dyn Error>> for Failure {
+    fn from(e: Box<dyn Error>) -> Failure {
+        Failure::new(FailureKind::Other, e.to_string())
+    }
+}
+
+/// Quotes the text as a JSON string
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Number of database connection attempts made when `--connect-attempts` is not given
+const DEFAULT_CONNECT_ATTEMPTS: u32 = 5;
+
+/// Schema used when the database does not yet have any participation schemas
+const DEFAULT_SCHEMA: &str = "real";
+
+/// Environment variable naming the default category when `--default-category` is not given
+const DEFAULT_CATEGORY_VAR: &str = "PARTICIPATION_DEFAULT_CATEGORY";
+
+/// Environment variables giving the database connection settings when the matching `--db-*`
+/// arguments are not given
+const DB_URL_VAR: &str = "PARTICIPATION_DB_URL";
+const DB_HOST_VAR: &str = "PARTICIPATION_DB_HOST";
+const DB_PORT_VAR: &str = "PARTICIPATION_DB_PORT";
+const DB_USER_VAR: &str = "PARTICIPATION_DB_USER";
+const DB_NAME_VAR: &str = "PARTICIPATION_DB_NAME";
+
+/// Environment variable with the secret key that `--anonymize pseudonym` derives pseudonyms with
+const PSEUDONYM_KEY_VAR: &str = "PARTICIPATION_PSEUDONYM_KEY";
+
+/// Summary of the arguments, shown by `--help` and after an unknown argument
+const USAGE: &str = "\
+Usage: participation-tracker [options] [roster]
+
+Options:
+  --roster <path>                  roster file to import (may also be given without --roster)
+  --roster-format <format>         blackboard (default) or canvas
+  --recorder <name>                name stored with each recorded event
+  --db-url <url>                   database connection URL, instead of the settings below
+  --db-host <dir|host>             Unix socket directory (starts with /) or TCP host name
+  --db-port <port>                 database port
+  --db-user <name>                 database user
+  --db-name <name>                 database name
+  --connect-attempts <n>           connection attempts before giving up (default 5)
+  --schema <name>                  schema to work in
+  --check                          check the schema and exit
+  --read-only                      open the schema without allowing any changes, for demos
+  --parse-roster <path>            print what is read from a roster and exit
+  --transcript <path>              append recorded events to this CSV file
+  --export-only <path>             export the summary and exit
+  --detailed                       with --export-only, break periods down by category
+  --include-dropped                with --export-only, also list dropped students
+  --section <name>                 with --export-only, list only students in this section
+  --export-encoding <encoding>     with --export-only, utf-8 (default) or utf-16le
+  --anonymize <identifier>         with --export-only, identify students by ub_id or pseudonym
+  --default-category <name>        category prefilled when recording
+  --default-satisfaction <value>   yes, no (default), or pending
+  --duplicate-window <seconds>     how recent an identical event must be to prompt (default 60)
+  --next-up-interval <seconds>     how often to pass over the next up suggestion (default 0)
+  --show-usernames                 list student matches with usernames
+  --name-matching <strategy>       fuzzy (default), prefix, or substring
+  --no-confirm-quit                quit without asking for confirmation
+  --class-time <HH:MM>             when class meets, for recording against yesterday (default 10:00)
+  --min-points <points>            list students with fewer points as at risk (default 5)
+  --refresh-on-quit                refresh the summary before quitting
+  --advance-delay <milliseconds>   ignore enter this soon after moving to a field (default 0)
+  --term <label>                   label a new schema with its term, such as \"Fall 2024 LATIN 101\"
+  --seed-demo <seed>               fill an empty schema with made-up students and events
+  --category-order <names>         comma-separated categories to list first in the legend
+  --no-match-highlighting          show student matches without emphasizing matched letters
+  --json-errors                    print errors as JSON
+  --help                           show this message and exit
+  --version                        show the version and exit";
+
+/// Command line arguments
+struct Args {
+    roster_path: Option<OsString>,
+    roster_format: gate::RosterFormat,
+    recorder: Option<String>,
+    connection: back::ConnectionSettings,
+    connect_attempts: u32,
+    // when given, the summary is exported here without starting the user interface
+    export_path: Option<OsString>,
+    schema: Option<String>,
+    // when set, the schema is checked for integrity without starting the user interface
+    check: bool,
+    // when given, this roster is parsed and printed without connecting to the database
+    parse_roster_path: Option<OsString>,
+    // where recorded events are appended as a backup; defaults under data/ when not given
+    transcript_path: Option<OsString>,
+    duplicate_window_secs: u32,
+    // first column of an export-only summary
+    export_identifier: model::ExportIdentifier,
+    // whether an export-only summary is broken down by category
+    detailed_export: bool,
+    // when set, the export also lists students who dropped
+    include_dropped: bool,
+    // when given, the export only lists students in this section
+    section: Option<String>,
+    export_encoding: gate::ExportEncoding,
+    default_category: Option<String>,
+    default_satisfaction: model::Satisfaction,
+    // seconds between advancing the main menu's next up suggestion; 0 leaves it in place
+    next_up_interval: u32,
+    show_usernames: bool,
+    name_matching: front::NameMatching,
+    confirm_quit: bool,
+    // time of day events recorded against yesterday's class are dated
+    class_time: NaiveTime,
+    // points below which a student is listed as at risk
+    min_points: i32,
+    refresh_on_quit: bool,
+    // milliseconds after moving to a field of the recording dialog during which enter is ignored
+    advance_delay_ms: u64,
+    // stored in the schema's metadata unless it already has a term label
+    term_label: Option<String>,
+    // when given, a schema without events or students is filled with demo data generated from this seed
+    demo_seed: Option<u64>,
+    // categories listed first in the legend, in this order; the rest follow alphabetically
+    category_order: Vec<String>,
+    highlight_matches: bool,
+}
+
+impl Args {
+    fn parse() -> Result<Args, Box<dyn Error>> {
+        let mut roster_path = None;
+        let mut roster_format = gate::RosterFormat::Blackboard;
+        let mut recorder = None;
+        let mut db_url = None;
+        let mut db_host = None;
+        let mut db_port = None;
+        let mut db_user = None;
+        let mut db_name = None;
+        let mut read_only = false;
+        let mut connect_attempts = DEFAULT_CONNECT_ATTEMPTS;
+        let mut export_path = None;
+        let mut schema = None;
+        let mut check = false;
+        let mut parse_roster_path = None;
+        let mut transcript_path = None;
+        let mut duplicate_window_secs = back::DEFAULT_DUPLICATE_WINDOW_SECS;
+        let mut export_identifier = None;
+        let mut detailed_export = false;
+        let mut include_dropped = false;
+        let mut section = None;
+        let mut export_encoding = None;
+        let mut default_category = None;
+        let mut default_satisfaction = model::Satisfaction::Unsatisfactory;
+        let mut next_up_interval = 0;
+        let mut show_usernames = false;
+        let mut name_matching = front::NameMatching::Fuzzy;
+        let mut confirm_quit = true;
+        let mut class_time = NaiveTime::from_hms(10, 0, 0);
+        let mut min_points = front::DEFAULT_MIN_POINTS;
+        let mut refresh_on_quit = false;
+        let mut advance_delay_ms = 0;
+        let mut term_label = None;
+        let mut demo_seed = None;
+        let mut category_order = vec![];
+        let mut highlight_matches = true;
+        let mut args = env::args_os().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--recorder" {
+                let value = args.next().ok_or("--recorder requires a name")?;
+                let value = value.into_string().map_err(|_| "--recorder name must be valid Unicode")?;
+                if value.chars().count() > 30 {
+                    return Err("--recorder name must be at most 30 characters".into());
+                }
+                recorder = Some(value);
+            } else if arg == "--roster-format" {
+                let value = args.next().ok_or("--roster-format requires blackboard or canvas")?;
+                roster_format = if value == "blackboard" {
+                    gate::RosterFormat::Blackboard
+                } else if value == "canvas" {
+                    gate::RosterFormat::Canvas
+                } else {
+                    return Err("--roster-format requires blackboard or canvas".into());
+                };
+            } else if arg == "--roster" {
+                let value = args.next().ok_or("--roster requires a path")?;
+                if roster_path.is_some() {
+                    return Err("Only one roster may be given".into());
                 }
+                roster_path = Some(value);
+            } else if arg == "--db-url" {
+                let value = args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .ok_or("--db-url requires a URL")?;
+                value.parse::<postgres::Config>().map_err(|e| format!("--db-url is not a valid connection URL: {}", e))?;
+                db_url = Some(value);
+            } else if arg == "--db-host" {
+                db_host = Some(args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .ok_or("--db-host requires a socket directory or host name")?);
+            } else if arg == "--db-port" {
+                db_port = Some(args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("--db-port requires a port number")?);
+            } else if arg == "--db-user" {
+                db_user = Some(args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .ok_or("--db-user requires a user name")?);
+            } else if arg == "--db-name" {
+                db_name = Some(args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .ok_or("--db-name requires a database name")?);
+            } else if arg == "--connect-attempts" {
+                connect_attempts = args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .and_then(|value| value.parse().ok())
+                    .filter(|value| *value > 0)
+                    .ok_or("--connect-attempts requires a positive number")?;
+            } else if arg == "--export-only" {
+                export_path = Some(args.next().ok_or("--export-only requires a path")?);
+            } else if arg == "--schema" {
+                let value = args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .ok_or("--schema requires a name")?;
+                // the schema name is interpolated into SQL statements
+                let valid = value.chars().next().is_some_and(|c| c.is_ascii_lowercase() || c == '_') &&
+                    value.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+                if !valid {
+                    return Err("--schema name must consist of lowercase letters, digits, and underscores".into());
+                }
+                schema = Some(value);
+            } else if arg == "--read-only" {
+                read_only = true;
+            } else if arg == "--check" {
+                check = true;
+            } else if arg == "--parse-roster" {
+                parse_roster_path = Some(args.next().ok_or("--parse-roster requires a path")?);
+            } else if arg == "--transcript" {
+                transcript_path = Some(args.next().ok_or("--transcript requires a path")?);
+            } else if arg == "--duplicate-window" {
+                duplicate_window_secs = args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("--duplicate-window requires a number of seconds")?;
+            } else if arg == "--anonymize" {
+                let value = args.next().ok_or("--anonymize requires ub_id or pseudonym")?;
+                export_identifier = if value == "ub_id" {
+                    Some(model::ExportIdentifier::UbId)
+                } else if value == "pseudonym" {
+                    let key = env::var(PSEUDONYM_KEY_VAR).ok()
+                        .filter(|value| !value.is_empty())
+                        .ok_or_else(|| format!("--anonymize pseudonym requires a secret key in {}", PSEUDONYM_KEY_VAR))?;
+                    Some(model::ExportIdentifier::Pseudonym(key.into_bytes()))
+                } else {
+                    return Err("--anonymize requires ub_id or pseudonym".into());
+                };
+            } else if arg == "--detailed" {
+                detailed_export = true;
+            } else if arg == "--include-dropped" {
+                include_dropped = true;
+            } else if arg == "--section" {
+                section = Some(args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .ok_or("--section requires a section name")?);
+            } else if arg == "--export-encoding" {
+                let value = args.next().ok_or("--export-encoding requires utf-8 or utf-16le")?;
+                export_encoding = if value == "utf-8" {
+                    Some(gate::ExportEncoding::Utf8)
+                } else if value == "utf-16le" {
+                    Some(gate::ExportEncoding::Utf16Le)
+                } else {
+                    return Err("--export-encoding requires utf-8 or utf-16le".into());
+                };
+            } else if arg == "--default-category" {
+                let value = args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .ok_or("--default-category requires a category name")?;
+                default_category = Some(value);
+            } else if arg == "--default-satisfaction" {
+                let value = args.next().ok_or("--default-satisfaction requires yes, no, or pending")?;
+                default_satisfaction = if value == "yes" {
+                    model::Satisfaction::Satisfactory
+                } else if value == "no" {
+                    model::Satisfaction::Unsatisfactory
+                } else if value == "pending" {
+                    model::Satisfaction::Pending
+                } else {
+                    return Err("--default-satisfaction requires yes, no, or pending".into());
+                };
+            } else if arg == "--next-up-interval" {
+                next_up_interval = args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("--next-up-interval requires a number of seconds")?;
+            } else if arg == "--show-usernames" {
+                show_usernames = true;
+            } else if arg == "--no-confirm-quit" {
+                confirm_quit = false;
+            } else if arg == "--class-time" {
+                class_time = args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .and_then(|value| NaiveTime::parse_from_str(&value, "%H:%M").ok())
+                    .ok_or("--class-time requires a time of day as HH:MM")?;
+            } else if arg == "--refresh-on-quit" {
+                refresh_on_quit = true;
+            } else if arg == "--advance-delay" {
+                advance_delay_ms = args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("--advance-delay requires a number of milliseconds")?;
+            } else if arg == "--term" {
+                term_label = Some(args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .filter(|value| !value.is_empty() && value.chars().count() <= 100)
+                    .ok_or("--term requires a label of at most 100 characters")?);
+            } else if arg == "--seed-demo" {
+                demo_seed = Some(args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("--seed-demo requires a number to seed the demo data with")?);
+            } else if arg == "--category-order" {
+                category_order = args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .map(|value| value.split(',').map(|name| name.trim().to_string()).filter(|name| !name.is_empty()).collect())
+                    .ok_or("--category-order requires comma-separated category names")?;
+            } else if arg == "--no-match-highlighting" {
+                highlight_matches = false;
+            } else if arg == "--min-points" {
+                min_points = args.next()
+                    .and_then(|value| value.into_string().ok())
+                    .and_then(|value| value.parse().ok())
+                    .ok_or("--min-points requires a number of points")?;
+            } else if arg == "--name-matching" {
+                let value = args.next().ok_or("--name-matching requires fuzzy, prefix, or substring")?;
+                name_matching = if value == "fuzzy" {
+                    front::NameMatching::Fuzzy
+                } else if value == "prefix" {
+                    front::NameMatching::Prefix
+                } else if value == "substring" {
+                    front::NameMatching::Substring
+                } else {
+                    return Err("--name-matching requires fuzzy, prefix, or substring".into());
+                };
+            } else if arg == "--json-errors" {
+                // already taken into account by main
+            } else if arg == "--help" || arg == "-h" {
+                println!("{}", USAGE);
+                process::exit(0);
+            } else if arg == "--version" || arg == "-V" {
+                println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+                process::exit(0);
+            } else if arg.to_string_lossy().starts_with('-') {
+                return Err(format!("Unknown argument: {:?}\n\n{}", arg, USAGE).into());
+            } else if roster_path.is_none() {
+                // a bare path is the roster, as before --roster existed
+                roster_path = Some(arg);
+            } else {
+                return Err(format!("Unexpected argument: {:?}\n\n{}", arg, USAGE).into());
+            }
+        }
+        if export_identifier.is_some() && export_path.is_none() {
+            return Err("--anonymize only applies with --export-only".into());
+        }
+        if detailed_export && export_path.is_none() {
+            return Err("--detailed only applies with --export-only".into());
+        }
+        if include_dropped && (export_path.is_none() || detailed_export) {
+            return Err("--include-dropped only applies with --export-only, without --detailed".into());
+        }
+        if section.is_some() && (export_path.is_none() || detailed_export) {
+            return Err("--section only applies with --export-only, without --detailed".into());
+        }
+        if export_encoding.is_some() && export_path.is_none() {
+            return Err("--export-encoding only applies with --export-only".into());
+        }
+        let export_encoding = export_encoding.unwrap_or(gate::ExportEncoding::Utf8);
+        let export_identifier = export_identifier.unwrap_or(model::ExportIdentifier::Username);
+        let default_category = default_category
+            .or_else(|| env::var(DEFAULT_CATEGORY_VAR).ok().filter(|value| !value.is_empty()));
+        let from_env = |var: &str| env::var(var).ok().filter(|value| !value.is_empty());
+        let db_port = match (db_port, from_env(DB_PORT_VAR)) {
+            (Some(port), _) => Some(port),
+            (None, Some(value)) => Some(value.parse().map_err(|_| format!("{} must be a port number", DB_PORT_VAR))?),
+            (None, None) => None,
+        };
+        let defaults = back::ConnectionSettings::default();
+        let db_url = match (db_url, from_env(DB_URL_VAR)) {
+            (Some(url), _) => Some(url),
+            (None, Some(url)) => {
+                url.parse::<postgres::Config>().map_err(|e| format!("{} is not a valid connection URL: {}", DB_URL_VAR, e))?;
+                Some(url)
+            },
+            (None, None) => None,
+        };
+        let connection = back::ConnectionSettings {
+            url: db_url,
+            host: db_host.or_else(|| from_env(DB_HOST_VAR)).unwrap_or(defaults.host),
+            port: db_port.unwrap_or(defaults.port),
+            user: db_user.or_else(|| from_env(DB_USER_VAR)).unwrap_or(defaults.user),
+            dbname: db_name.or_else(|| from_env(DB_NAME_VAR)).unwrap_or(defaults.dbname),
+            read_only: read_only,
+        };
+        if read_only && roster_path.is_some() {
+            return Err("a roster cannot be imported with --read-only".into());
+        }
+        if read_only && term_label.is_some() {
+            return Err("a term label cannot be stored with --read-only".into());
+        }
+        if read_only && demo_seed.is_some() {
+            return Err("demo data cannot be added with --read-only".into());
+        }
+        Ok(Args {
+            roster_path: roster_path,
+            roster_format: roster_format,
+            recorder: recorder,
+            connection: connection,
+            connect_attempts: connect_attempts,
+            export_path: export_path,
+            schema: schema,
+            check: check,
+            parse_roster_path: parse_roster_path,
+            transcript_path: transcript_path,
+            duplicate_window_secs: duplicate_window_secs,
+            export_identifier: export_identifier,
+            detailed_export: detailed_export,
+            include_dropped: include_dropped,
+            section: section,
+            export_encoding: export_encoding,
+            default_category: default_category,
+            default_satisfaction: default_satisfaction,
+            next_up_interval: next_up_interval,
+            show_usernames: show_usernames,
+            name_matching: name_matching,
+            confirm_quit: confirm_quit,
+            class_time: class_time,
+            min_points: min_points,
+            refresh_on_quit: refresh_on_quit,
+            advance_delay_ms: advance_delay_ms,
+            term_label: term_label,
+            demo_seed: demo_seed,
+            category_order: category_order,
+            highlight_matches: highlight_matches,
+        })
+    }
+}
+
+fn run() -> Result<(), Failure> {
+    let args = Args::parse()?;
+    if let Some(path) = args.parse_roster_path {
+        return parse_roster_only(path, args.roster_format);
+    }
+    let roster = match args.roster_path {
+        Some(path) => {
+            let r = gate::read_roster(path, args.roster_format)
+                .map_err(|e| Failure::new(FailureKind::Roster, format!("Error in reading roster:\n{}", e)))?;
+            for skipped in r.skipped() {
+                println!("Skipped malformed roster row at line {}: {}", skipped.line, skipped.contents);
             }
+            Some(r)
         },
         None => None
     };
-    let client = match back::get_db_conn(&roster, schema) {
-        Ok(c) => Arc::new(Mutex::new(c)),
-        Err(e) => {
-            println!("Database error:");
-            println!("{:?}", e);
-            process::exit(1);
+    let target = format!("{} over {}", args.connection.describe(), args.connection.transport());
+    println!("Connecting to {}", target);
+    let connection = &args.connection;
+    let attempts = args.connect_attempts;
+    let mut show_retry = |attempt: u32, e: &postgres::Error, delay: Duration| {
+        println!("Could not connect to database over {} (attempt {} of {}): {}", connection.transport(), attempt, attempts, e);
+        println!("Retrying in {} seconds", delay.as_secs());
+    };
+    let mut client = back::get_db_conn(connection, attempts, &mut show_retry)
+        .map_err(|e| {
+            let hint = connection.failure_hint(&e).map(|hint| format!("\n{}", hint)).unwrap_or_default();
+            Failure::new(FailureKind::Connect, format!("Database error connecting to {}:\n{:?}{}", target, e, hint))
+        })?;
+    let schemas = back::list_participation_schemas(&mut client)
+        .map_err(|e| Failure::new(FailureKind::Database, format!("Couldn't list schemas\n{}", e)))?;
+    let schema = match (args.schema, schemas.len()) {
+        (Some(s), _) => s,
+        (None, 0) => DEFAULT_SCHEMA.to_string(),
+        (None, 1) => schemas[0].clone(),
+        (None, _) if args.export_path.is_some() || args.check => {
+            return Err(Failure::new(FailureKind::Other, format!("Multiple schemas found ({}); choose one with --schema", schemas.join(", "))));
         },
+        (None, _) => front::choose_schema(&schemas)?,
     };
-    let categories = match back::get_categories(&mut client.lock().unwrap(), schema) {
-        Ok(c) => c,
-        Err(e) => {
-            println!("Couldn't get categories");
-            println!("{:?}", e);
-            process::exit(1);
+    let schema = &*schema;
+    if args.check {
+        return check_only(&mut client, schema);
+    }
+    // large rosters take several seconds to import, so count the students off on one line
+    let mut show_progress = |imported: usize, total: usize| {
+        print!("\rImported {}/{} students.", imported, total);
+        if imported == total {
+            println!();
         }
+        let _ = io::stdout().flush();
     };
-    let students = match back::get_students(&mut client.lock().unwrap(), schema) {
-        Ok(c) => c,
-        Err(e) => {
-            println!("Couldn't get students");
-            println!("{:?}", e);
-            process::exit(1);
+    if args.connection.read_only {
+        // setting up a missing schema would be a write, so there has to be one already
+        if !schemas.iter().any(|s| s == schema) {
+            return Err(Failure::new(FailureKind::Other, format!("Schema {} does not exist; --read-only can only open an existing schema", schema)));
         }
-    };
-    if students.len() <= 0 {
-        println!("No students in database; exiting");
-        println!("(If you would like to add students to the database or update them, run the program with the path to the student roster file as the first argument)");
-        process::exit(1);
+    } else {
+        let disambiguated = back::initialize_db(&mut client, &roster, schema, &mut show_progress)?;
+        for name in disambiguated {
+            println!("Students sharing a name were told apart by username: stored as {}", name);
+        }
+        if let Some(term_label) = &args.term_label {
+            let stored = back::set_term_label(&mut client, schema, term_label)?;
+            if !stored {
+                println!("Schema {} already has a term label; --term was not stored", schema);
+            }
+        }
+        if let Some(seed) = args.demo_seed {
+            let added = back::seed_demo_data(&mut client, schema, seed)?;
+            match added {
+                Some(added) => println!("Added {} demo events for made-up students", added),
+                None => {
+                    return Err(Failure::new(FailureKind::Other, format!("Schema {} already has events or students; --seed-demo only fills an empty schema", schema)));
+                },
+            }
+        }
+    }
+    let client = Arc::new(Mutex::new(client));
+    let categories = back::get_categories(&mut client.lock().unwrap(), schema)
+        .map_err(|e| Failure::new(FailureKind::Database, format!("Couldn't get categories\n{:?}", e)))?;
+    let students = back::get_students(&mut client.lock().unwrap(), schema)
+        .map_err(|e| Failure::new(FailureKind::Database, format!("Couldn't get students\n{:?}", e)))?;
+    let mut event_recorder = back::get_event_recorder(Arc::clone(&client), schema, args.recorder.as_deref(), args.connection)
+        .map_err(|e| Failure::new(FailureKind::Database, format!("Could not prepare statements:\n{}", e)))?;
+    event_recorder.set_duplicate_window(args.duplicate_window_secs);
+    if let Some(default_category) = &args.default_category {
+        if !categories.iter().any(|c| c.name == *default_category) {
+            let names: Vec<&str> = categories.iter().map(|c| &*c.name).collect();
+            return Err(Failure::new(FailureKind::Other, format!("Unknown default category {}; choose one of: {}", default_category, names.join(", "))));
+        }
+    }
+    event_recorder.set_default_category(args.default_category.as_deref());
+    if let Some(unknown) = args.category_order.iter().find(|name| !categories.iter().any(|c| c.name == **name)) {
+        let names: Vec<&str> = categories.iter().map(|c| &*c.name).collect();
+        return Err(Failure::new(FailureKind::Other, format!("Unknown category {} in --category-order; choose from: {}", unknown, names.join(", "))));
+    }
+    event_recorder.set_category_order(&args.category_order);
+    event_recorder.set_default_satisfaction(args.default_satisfaction);
+    if let Some(path) = args.export_path {
+        return export_only(&mut event_recorder, &path, args.export_identifier, args.detailed_export, args.include_dropped, args.section.as_deref(), args.export_encoding);
     }
-    let event_recorder = back::get_event_recorder(Arc::clone(&client), schema);
     let students = Rc::new(students);
-    let picker = back::get_student_picker(Rc::clone(&students));
-    front::cli(students, categories, picker, event_recorder);
+    let transcript_path = args.transcript_path
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(format!("data/transcript_{}.csv", schema)));
+    event_recorder.set_transcript_path(Some(transcript_path));
+    let picker_path = PathBuf::from(format!("data/picker_{}.txt", schema));
+    let picker = back::get_student_picker(Rc::clone(&students), Some(picker_path));
+    let preferences = front::Preferences {
+        next_up_interval: args.next_up_interval,
+        show_usernames: args.show_usernames,
+        name_matching: args.name_matching,
+        confirm_quit: args.confirm_quit,
+        class_time: args.class_time,
+        min_points: args.min_points,
+        refresh_on_quit: args.refresh_on_quit,
+        advance_delay: Duration::from_millis(args.advance_delay_ms),
+        highlight_matches: args.highlight_matches,
+    };
+    Ok(front::cli(students, categories, picker, event_recorder, preferences)?)
+}
+
+/// Prints the students read from the roster, without touching the database
+fn parse_roster_only(path: OsString, format: gate::RosterFormat) -> Result<(), Failure> {
+    let roster = gate::read_roster(path, format)
+        .map_err(|e| Failure::new(FailureKind::Roster, format!("Error in reading roster:\n{}", e)))?;
+    for (ub_id, name, username) in roster.iter() {
+        println!("{}\t{}\t{}", ub_id, name, username);
+    }
+    for skipped in roster.skipped() {
+        println!("Skipped malformed roster row at line {}: {}", skipped.line, skipped.contents);
+    }
+    println!("Read {}", roster);
+    Ok(())
+}
+
+/// Exports the summary to the given path without starting the user interface
+fn export_only(event_recorder: &mut back::EventRecorder, path: &OsString, identifier: model::ExportIdentifier, detailed: bool, include_dropped: bool, section: Option<&str>, encoding: gate::ExportEncoding) -> Result<(), Failure> {
+    // the file is only created once there is something to write to it
+    let create = || File::create(path).map_err(AppError::Io);
+    if detailed {
+        let summary = event_recorder.get_detailed_summary()?;
+        let term_label = event_recorder.term_label()?;
+        gate::export_detailed_summary(summary, term_label.as_deref(), identifier, encoding, &mut create()?).map_err(AppError::Io)?;
+    } else {
+        if let Some(section) = section {
+            // a misspelled section would otherwise export an empty summary
+            let sections = event_recorder.sections()?;
+            if sections.is_empty() {
+                return Err(Failure::new(FailureKind::Other, format!("No section {}: no roster imported into this schema gave sections", section)));
+            }
+            if !sections.iter().any(|s| s == section) {
+                return Err(Failure::new(FailureKind::Other, format!("No enrolled student is in section {}; sections are: {}", section, sections.join(", "))));
+            }
+        }
+        let rows = event_recorder.get_summary(include_dropped, section)?;
+        gate::export_summary(rows, identifier, encoding, &mut create()?).map_err(AppError::Io)?;
+    }
+    println!("Finished export:\n{}", path.to_string_lossy());
+    Ok(())
+}
+
+/// Prints a report of the schema's integrity without starting the user interface
+fn check_only(client: &mut postgres::Client, schema: &str) -> Result<(), Failure> {
+    let results = back::check_schema(client, schema)?;
+    for result in &results {
+        println!("[{}] {}", if result.passed { "PASS" } else { "FAIL" }, result.description);
+    }
+    let failed = results.iter().filter(|r| !r.passed).count();
+    if failed > 0 {
+        return Err(Failure::new(FailureKind::Database, format!("Schema {} failed {} of {} checks", schema, failed, results.len())));
+    }
+    println!("Schema {} passed all {} checks", schema, results.len());
+    Ok(())
 }
@@ -1,4 +1,8 @@
+use std::fmt;
+
 use chrono::{DateTime, Local};
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
 
 #[derive(Clone, Debug)]
 pub struct Category {
@@ -11,6 +15,49 @@ impl Category {
     pub fn new(db_id: i32, name: String, first_entered: DateTime<Local>) -> Category {
         Category { db_id, name, first_entered }
     }
+
+    pub fn first_entered(&self) -> &DateTime<Local> {
+        &self.first_entered
+    }
+}
+
+/// Whether a participation event earned a point
+///
+/// Stored in the database as a nullable boolean, with NULL meaning the event has not been
+/// evaluated yet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Satisfaction {
+    Unsatisfactory,
+    Satisfactory,
+    Pending,
+}
+
+impl Satisfaction {
+    pub fn from_db(value: Option<bool>) -> Satisfaction {
+        match value {
+            Some(true) => Satisfaction::Satisfactory,
+            Some(false) => Satisfaction::Unsatisfactory,
+            None => Satisfaction::Pending,
+        }
+    }
+
+    pub fn to_db(self) -> Option<bool> {
+        match self {
+            Satisfaction::Satisfactory => Some(true),
+            Satisfaction::Unsatisfactory => Some(false),
+            Satisfaction::Pending => None,
+        }
+    }
+}
+
+impl fmt::Display for Satisfaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Satisfaction::Satisfactory => write!(f, "yes"),
+            Satisfaction::Unsatisfactory => write!(f, "no"),
+            Satisfaction::Pending => write!(f, "pending"),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -20,24 +67,255 @@ pub struct Student {
     pub name: String,
     first_entered: DateTime<Local>,
     status_id: i32,
-    last_updated: DateTime<Local>
+    last_updated: DateTime<Local>,
+    // rows entered before usernames were tracked may not have one
+    pub username: Option<String>,
 }
 
 impl Student {
-    pub fn new(db_id: i32, ub_id: String, name: String, first_entered: DateTime<Local>, status_id: i32, last_updated: DateTime<Local>) -> Student {
-        Student { db_id, ub_id, name, first_entered, status_id, last_updated }
+    pub fn new(db_id: i32, ub_id: String, name: String, first_entered: DateTime<Local>, status_id: i32, last_updated: DateTime<Local>, username: Option<String>) -> Student {
+        Student { db_id, ub_id, name, first_entered, status_id, last_updated, username }
+    }
+
+    pub fn first_entered(&self) -> &DateTime<Local> {
+        &self.first_entered
+    }
+
+    pub fn status_id(&self) -> i32 {
+        self.status_id
+    }
+
+    pub fn last_updated(&self) -> &DateTime<Local> {
+        &self.last_updated
+    }
+}
+
+/// A roster row that could not be read as a student record
+#[derive(Clone, Debug)]
+pub struct SkippedRow {
+    pub line: u64,
+    pub contents: String,
+}
+
+/// Wording in the recording dialog that a deployment can change, for example to be clearer to
+/// substitute TAs or to localize it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Labels {
+    // heading above the choice of whether an event is satisfactory
+    pub satisfactory_header: String,
+    // what the status line calls that choice while it is focused
+    pub satisfactory_prompt: String,
+}
+
+impl Default for Labels {
+    fn default() -> Labels {
+        Labels {
+            satisfactory_header: "?".to_string(),
+            satisfactory_prompt: "Satisfactory".to_string(),
+        }
+    }
+}
+
+/// Which value identifies each student in the first column of an export
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExportIdentifier {
+    // what Blackboard expects when importing grades
+    Username,
+    UbId,
+    // a hash of the UB ID keyed with this secret, for sharing data without revealing who is who
+    Pseudonym(Vec<u8>),
+}
+
+impl ExportIdentifier {
+    pub fn header(&self) -> &'static str {
+        match self {
+            ExportIdentifier::Username => "Username",
+            ExportIdentifier::UbId => "UB ID",
+            ExportIdentifier::Pseudonym(_) => "Pseudonym",
+        }
+    }
+}
+
+/// One student's count of satisfactory events in each period as the summary queries give it,
+/// before it is turned into a `SummaryRow`
+#[derive(Clone, Debug, PartialEq)]
+pub struct SummaryRecord {
+    // students entered before usernames were tracked have none
+    pub username: Option<String>,
+    pub ub_id: String,
+    pub periods: Vec<i64>,
+}
+
+/// One student's count of satisfactory events in each grading period
+#[derive(Clone, Debug)]
+pub struct SummaryRow {
+    pub username: String,
+    pub ub_id: String,
+    pub periods: Vec<i64>,
+}
+
+impl SummaryRow {
+    pub fn identifier(&self, kind: &ExportIdentifier) -> String {
+        match kind {
+            ExportIdentifier::Username => self.username.clone(),
+            ExportIdentifier::UbId => self.ub_id.clone(),
+            ExportIdentifier::Pseudonym(key) => {
+                // there are few enough UB IDs to hash every one of them, so without the key the
+                // pseudonyms could be traced back; with the same key, the same student gets the
+                // same pseudonym in every export
+                let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+                mac.update(self.ub_id.as_bytes());
+                let digest = mac.finalize().into_bytes();
+                let hex: String = digest[..6].iter().map(|b| format!("{:02x}", b)).collect();
+                format!("student-{}", hex)
+            }
+        }
+    }
+
+    /// Formats the row as a line of the tab-separated export, without the trailing newline
+    pub fn to_line(&self, kind: &ExportIdentifier) -> String {
+        let mut line = format!("\"{}\"", self.identifier(kind));
+        for points in &self.periods {
+            line.push_str(&format!("\t{}", points));
+        }
+        line
     }
 }
 
+/// Formats the row as a line of the tab-separated export, identified by username
+impl fmt::Display for SummaryRow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_line(&ExportIdentifier::Username))
+    }
+}
+
+/// One student's count of satisfactory events in each grading period, broken down by category
+#[derive(Clone, Debug)]
+pub struct DetailedSummaryRow {
+    // totals over all categories
+    pub summary: SummaryRow,
+    // counts for each period, in the order of the categories of the detailed summary
+    pub by_category: Vec<Vec<i64>>,
+}
+
+impl DetailedSummaryRow {
+    /// Formats the row as a line of the tab-separated export, without the trailing newline; each
+    /// period's total comes before its per-category counts
+    pub fn to_line(&self, kind: &ExportIdentifier) -> String {
+        let mut line = format!("\"{}\"", self.summary.identifier(kind));
+        for (total, counts) in self.summary.periods.iter().zip(&self.by_category) {
+            line.push_str(&format!("\t{}", total));
+            for count in counts {
+                line.push_str(&format!("\t{}", count));
+            }
+        }
+        line
+    }
+}
+
+/// The summary broken down by category, for reviewing how students participate
+#[derive(Clone, Debug)]
+pub struct DetailedSummary {
+    pub categories: Vec<String>,
+    pub rows: Vec<DetailedSummaryRow>,
+}
+
+/// A recorded participation event of a particular student
+#[derive(Clone, Debug)]
+pub struct EventRow {
+    pub db_id: i32,
+    pub category: String,
+    pub first_entered: DateTime<Local>,
+    pub satisfactory: Satisfaction,
+    pub recorder: Option<String>,
+}
+
+/// An enrolled student's name along with how much they have participated
+#[derive(Clone, Debug)]
+pub struct StudentActivity {
+    pub name: String,
+    pub points: i32,
+    pub last_active: Option<DateTime<Local>>,
+}
+
+/// A name entered for recording that matches nothing in the database
+#[derive(Clone, Debug, PartialEq)]
+pub enum UnknownName {
+    Student(String),
+    Category(String),
+}
+
+/// An enrolled student with fewer points than the minimum, along with what identifies them in an
+/// export
+#[derive(Clone, Debug)]
+pub struct AtRiskStudent {
+    pub name: String,
+    pub username: String,
+    pub ub_id: String,
+    pub points: i32,
+}
+
+impl AtRiskStudent {
+    /// Gives the student's points as a single-column summary row, for exporting
+    pub fn to_summary_row(&self) -> SummaryRow {
+        SummaryRow {
+            username: self.username.clone(),
+            ub_id: self.ub_id.clone(),
+            periods: vec![i64::from(self.points)],
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct Roster {
     ub_ids: Vec<String>,
     names: Vec<String>,
     usernames: Vec<String>,
+    // empty unless the roster file says which section each student is in
+    sections: Vec<Option<String>>,
+    skipped: Vec<SkippedRow>,
 }
 
 impl Roster {
-    pub fn new(ub_ids: Vec<String>, names: Vec<String>, usernames: Vec<String>) -> Roster {
-        Roster { ub_ids: ub_ids, names: names, usernames: usernames }
+    pub fn new(ub_ids: Vec<String>, names: Vec<String>, usernames: Vec<String>, skipped: Vec<SkippedRow>) -> Roster {
+        Roster { ub_ids: ub_ids, names: names, usernames: usernames, sections: vec![], skipped: skipped }
+    }
+
+    /// Gives the roster's students sections, in roster order
+    pub fn with_sections(mut self, sections: Vec<Option<String>>) -> Roster {
+        self.sections = sections;
+        self
+    }
+
+    /// Number of complete student records in the roster
+    pub fn len(&self) -> usize {
+        self.ub_ids.len().min(self.names.len()).min(self.usernames.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn ub_ids(&self) -> &[String] {
+        &self.ub_ids
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn usernames(&self) -> &[String] {
+        &self.usernames
+    }
+
+    /// Section of the student at the position in the roster, if the roster gives one
+    pub fn section(&self, i: usize) -> Option<&str> {
+        self.sections.get(i).and_then(|section| section.as_deref())
+    }
+
+    /// Rows of the roster file that were skipped because they were malformed
+    pub fn skipped(&self) -> &[SkippedRow] {
+        &self.skipped
     }
 
     pub fn iter(&self) -> RosterIterator {
@@ -48,6 +326,16 @@ impl Roster {
     }
 }
 
+impl fmt::Display for Roster {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "roster of {} students", self.len())?;
+        if !self.skipped.is_empty() {
+            write!(f, " ({} malformed rows skipped)", self.skipped.len())?;
+        }
+        Ok(())
+    }
+}
+
 pub struct RosterIterator<'a> {
     inner: &'a Roster,
     pos: usize,